@@ -1,12 +1,24 @@
 use std::fmt;
+use crate::bigint::BigInt;
+use crate::span::Span;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum Operator {
     Add,
     Sub,
     Mul,
     Div,
-    Exp
+    Exp,
+    ElemMul,
+    ElemDiv,
+    ElemExp,
+    Less,
+    Greater,
+    LessEqual,
+    GreaterEqual,
+    Equal,
+    NotEqual,
+    Range,
 }
 
 impl fmt::Display for Operator {
@@ -17,6 +29,16 @@ impl fmt::Display for Operator {
             Operator::Mul => write!(f, "*"),
             Operator::Div => write!(f, "/"),
             Operator::Exp => write!(f, "^"),
+            Operator::ElemMul => write!(f, ".*"),
+            Operator::ElemDiv => write!(f, "./"),
+            Operator::ElemExp => write!(f, ".^"),
+            Operator::Less => write!(f, "<"),
+            Operator::Greater => write!(f, ">"),
+            Operator::LessEqual => write!(f, "<="),
+            Operator::GreaterEqual => write!(f, ">="),
+            Operator::Equal => write!(f, "=="),
+            Operator::NotEqual => write!(f, "!="),
+            Operator::Range => write!(f, ".."),
         }
     }
 }
@@ -26,78 +48,156 @@ impl<'a> From<&'a str> for Operator {
         match item {
             "+" => Operator::Add,
             "-" => Operator::Sub,
-            "*" => Operator::Mul,
-            "/" => Operator::Div,
+            "*" | "×" => Operator::Mul,
+            "/" | "÷" => Operator::Div,
             "^" => Operator::Exp,
+            ".*" => Operator::ElemMul,
+            "./" => Operator::ElemDiv,
+            ".^" => Operator::ElemExp,
+            "<" => Operator::Less,
+            ">" => Operator::Greater,
+            "<=" | "≤" => Operator::LessEqual,
+            ">=" | "≥" => Operator::GreaterEqual,
+            "==" => Operator::Equal,
+            "!=" | "≠" => Operator::NotEqual,
+            ".." => Operator::Range,
             _ => panic!("unknown operator")
         }
     }
 }
 
-#[derive(Debug)]
-pub enum Node<'a> {
+#[derive(Debug, Clone)]
+pub enum NodeKind {
     Number(i64),
     Decimal(f64),
-    Array(Vec<Node<'a>>),
-    Variable(&'a str),
+    /// An integer literal too large for a `Number`.
+    BigInt(BigInt),
+    /// An imaginary literal such as `4i`.
+    Imaginary(f64),
+    Bool(bool),
+    String(String),
+    Array(Vec<Node>),
+    Variable(String),
     Call {
-        function: &'a str,
-        arguments: Vec<Node<'a>>,
+        function: String,
+        arguments: Vec<Node>,
     },
     Assign {
-        lhs: &'a str,
-        rhs: Box<Node<'a>>
+        lhs: String,
+        indices: Vec<Index>,
+        rhs: Box<Node>
+    },
+    Index {
+        target: Box<Node>,
+        indices: Vec<Index>,
     },
     UnaryOp {
         op: Operator,
-        rhs: Box<Node<'a>>
+        rhs: Box<Node>
     },
     BinaryOp {
         op: Operator,
-        lhs: Box<Node<'a>>,
-        rhs: Box<Node<'a>>
+        lhs: Box<Node>,
+        rhs: Box<Node>
     },
     Declaration {
-        name: &'a str,
-        kind: &'a str,
+        name: String,
+        kind: String,
     },
     Function {
-        name: &'a str,
-        parameters: Vec<Node<'a>>,
-        body: Block<'a>
+        name: String,
+        parameters: Vec<Node>,
+        body: Block,
+        /// Text of the `///` comments written just before the function.
+        doc: Option<String>,
+    },
+    If {
+        condition: Box<Node>,
+        then: Block,
+        otherwise: Option<Block>,
+    },
+    While {
+        condition: Box<Node>,
+        body: Block,
+    },
+    For {
+        variable: String,
+        iterable: Box<Node>,
+        body: Block,
+    },
+}
+
+#[derive(Debug, Clone)]
+pub struct Node {
+    pub kind: NodeKind,
+    pub span: Span,
+}
+
+impl Node {
+    pub fn new(kind: NodeKind, span: Span) -> Node {
+        Node {
+            kind,
+            span,
+        }
     }
 }
 
-impl<'a> fmt::Display for Node<'a> {
+impl fmt::Display for Node {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.kind.fmt(f)
+    }
+}
+
+impl fmt::Display for NodeKind {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
-            Node::Number(value) => write!(f, "NumberNode({})", value),
-            Node::Decimal(value) => write!(f, "DecimalNode({})", value),
-            Node::Array(value) => write!(f, "ArrayNode({:?})", value),
-            Node::Variable(value) => write!(f, "VariableNode({})", value),
-            Node::Call { function, arguments } => write!(f, "CallNode({}, {:?})", function, arguments),
-            Node::Assign { lhs, rhs } => write!(f, "AssignNode({}, {})", lhs, rhs),
-            Node::UnaryOp { op, rhs} => write!(f, "UnaryOpNode({}, {})", op, rhs),
-            Node::BinaryOp { op, lhs, rhs} => write!(f, "BinaryOpNode({}, {}, {})", lhs, op, rhs),
-            Node::Declaration { name, kind } => write!(f, "Declaration({}, {})", name, kind),
-            Node::Function { name, parameters, body } => {
+            NodeKind::Number(value) => write!(f, "NumberNode({})", value),
+            NodeKind::Decimal(value) => write!(f, "DecimalNode({})", value),
+            NodeKind::BigInt(value) => write!(f, "BigIntNode({})", value),
+            NodeKind::Imaginary(value) => write!(f, "ImaginaryNode({})", value),
+            NodeKind::Bool(value) => write!(f, "BoolNode({})", value),
+            NodeKind::String(value) => write!(f, "StringNode({:?})", value),
+            NodeKind::Array(value) => write!(f, "ArrayNode({:?})", value),
+            NodeKind::Variable(value) => write!(f, "VariableNode({})", value),
+            NodeKind::Call { function, arguments } => write!(f, "CallNode({}, {:?})", function, arguments),
+            NodeKind::Assign { lhs, indices, rhs } if indices.is_empty() => write!(f, "AssignNode({}, {})", lhs, rhs),
+            NodeKind::Assign { lhs, indices, rhs } => write!(f, "AssignNode({}{:?}, {})", lhs, indices, rhs),
+            NodeKind::Index { target, indices } => write!(f, "IndexNode({}, {:?})", target, indices),
+            NodeKind::UnaryOp { op, rhs} => write!(f, "UnaryOpNode({}, {})", op, rhs),
+            NodeKind::BinaryOp { op, lhs, rhs} => write!(f, "BinaryOpNode({}, {}, {})", lhs, op, rhs),
+            NodeKind::Declaration { name, kind } => write!(f, "Declaration({}, {})", name, kind),
+            NodeKind::Function { name, parameters, .. } => {
                 write!(f, "Function({}", name)?;
 
-                let len = parameters.len();
+                for item in parameters.iter() {
+                    write!(f, ", {}", item)?;
+                }
 
-                for (i, item) in parameters.iter().enumerate() {
-                    write!(f, "{}", item)?;
+                write!(f, ")")
+            },
+            NodeKind::If { condition, then, otherwise } => {
+                write!(f, "IfNode({}, {:?}", condition, then)?;
 
-                    if i < len - 1 {
-                        write!(f, ", ")?;
-                    }
+                if let Some(block) = otherwise {
+                    write!(f, ", {:?}", block)?;
                 }
 
                 write!(f, ")")
             },
+            NodeKind::While { condition, body } => write!(f, "WhileNode({}, {:?})", condition, body),
+            NodeKind::For { variable, iterable, body } => write!(f, "ForNode({}, {}, {:?})", variable, iterable, body),
         }
     }
 }
 
-#[derive(Debug)]
-pub struct Block<'a>(pub Vec<Node<'a>>);
\ No newline at end of file
+#[derive(Debug, Clone)]
+pub struct Block(pub Vec<Node>);
+
+/// One comma-separated part of an index expression such as `M[:, 1..3]`.
+#[derive(Debug, Clone)]
+pub enum Index {
+    /// `:`, selecting every element along an axis
+    All,
+    /// a single position, or a `start..end` slice
+    Expr(Node),
+}
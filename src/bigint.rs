@@ -0,0 +1,439 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::ops;
+use std::str::FromStr;
+use crate::error::{Error, ErrorCode};
+
+/// The largest result `BigInt::pow` computes, in bits (about 40,000 decimal digits).
+pub const MAX_POW_BITS: u64 = 1 << 17;
+
+/// A signed integer of unbounded size.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct BigInt {
+    negative: bool,
+    /// Base 2^32 digits, least significant first, without trailing zeros.
+    limbs: Vec<u32>,
+}
+
+fn trim(limbs: &mut Vec<u32>) {
+    while limbs.last() == Some(&0) {
+        limbs.pop();
+    }
+}
+
+fn cmp_limbs(a: &[u32], b: &[u32]) -> Ordering {
+    a.len().cmp(&b.len()).then_with(|| a.iter().rev().cmp(b.iter().rev()))
+}
+
+fn add_limbs(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let (long, short) = if a.len() >= b.len() { (a, b) } else { (b, a) };
+    let mut out = Vec::with_capacity(long.len() + 1);
+    let mut carry = 0u64;
+
+    for (i, &x) in long.iter().enumerate() {
+        let sum = x as u64 + *short.get(i).unwrap_or(&0) as u64 + carry;
+
+        out.push(sum as u32);
+        carry = sum >> 32;
+    }
+
+    if carry > 0 {
+        out.push(carry as u32);
+    }
+
+    out
+}
+
+/// `a - b`, where `a >= b`.
+fn sub_limbs(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut out = Vec::with_capacity(a.len());
+    let mut borrow = 0i64;
+
+    for (i, &x) in a.iter().enumerate() {
+        let mut diff = x as i64 - *b.get(i).unwrap_or(&0) as i64 - borrow;
+
+        borrow = 0;
+
+        if diff < 0 {
+            diff += 1 << 32;
+            borrow = 1;
+        }
+
+        out.push(diff as u32);
+    }
+
+    trim(&mut out);
+
+    out
+}
+
+fn mul_limbs(a: &[u32], b: &[u32]) -> Vec<u32> {
+    if a.is_empty() || b.is_empty() {
+        return Vec::new();
+    }
+
+    let mut out = vec![0u32; a.len() + b.len()];
+
+    for (i, &x) in a.iter().enumerate() {
+        let mut carry = 0u64;
+
+        for (j, &y) in b.iter().enumerate() {
+            let cur = out[i + j] as u64 + x as u64 * y as u64 + carry;
+
+            out[i + j] = cur as u32;
+            carry = cur >> 32;
+        }
+
+        out[i + b.len()] = carry as u32;
+    }
+
+    trim(&mut out);
+
+    out
+}
+
+/// Shifts left by one bit and sets the lowest bit to `bit`.
+fn shl1(limbs: &mut Vec<u32>, bit: u32) {
+    let mut carry = bit;
+
+    for limb in limbs.iter_mut() {
+        let next = *limb >> 31;
+
+        *limb = (*limb << 1) | carry;
+        carry = next;
+    }
+
+    if carry != 0 {
+        limbs.push(carry);
+    }
+}
+
+/// Quotient and remainder of `a / b` for a non-zero `b`.
+fn divmod_limbs(a: &[u32], b: &[u32]) -> (Vec<u32>, Vec<u32>) {
+    if cmp_limbs(a, b) == Ordering::Less {
+        return (Vec::new(), a.to_vec());
+    }
+
+    let mut q = vec![0u32; a.len()];
+
+    if let [d] = b {
+        let d = *d as u64;
+        let mut r = 0u64;
+
+        for i in (0..a.len()).rev() {
+            let cur = (r << 32) | a[i] as u64;
+
+            q[i] = (cur / d) as u32;
+            r = cur % d;
+        }
+
+        let mut r = vec![r as u32];
+
+        trim(&mut q);
+        trim(&mut r);
+
+        return (q, r);
+    }
+
+    // binary long division
+    let mut r = Vec::new();
+
+    for i in (0..a.len() * 32).rev() {
+        shl1(&mut r, (a[i / 32] >> (i % 32)) & 1);
+
+        if cmp_limbs(&r, b) != Ordering::Less {
+            r = sub_limbs(&r, b);
+            q[i / 32] |= 1 << (i % 32);
+        }
+    }
+
+    trim(&mut q);
+
+    (q, r)
+}
+
+impl BigInt {
+    fn from_parts(negative: bool, mut limbs: Vec<u32>) -> BigInt {
+        trim(&mut limbs);
+
+        BigInt {
+            negative: negative && !limbs.is_empty(),
+            limbs,
+        }
+    }
+
+    pub fn zero() -> BigInt {
+        BigInt::default()
+    }
+
+    pub fn one() -> BigInt {
+        BigInt::from(1)
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.limbs.is_empty()
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.negative
+    }
+
+    pub fn abs(&self) -> BigInt {
+        BigInt::from_parts(false, self.limbs.clone())
+    }
+
+    pub fn to_i64(&self) -> Option<i64> {
+        if self.limbs.len() > 2 {
+            return None;
+        }
+
+        let magnitude = self.limbs.iter().rev().fold(0u64, |acc, limb| (acc << 32) | *limb as u64) as i128;
+
+        i64::try_from(if self.negative { -magnitude } else { magnitude }).ok()
+    }
+
+    pub fn to_f64(&self) -> f64 {
+        let magnitude = self.limbs.iter().rev().fold(0.0, |acc, limb| acc * 4294967296.0 + *limb as f64);
+
+        if self.negative { -magnitude } else { magnitude }
+    }
+
+    /// Truncating division, returning `None` when dividing by zero.
+    pub fn div_rem(&self, other: &BigInt) -> Option<(BigInt, BigInt)> {
+        if other.is_zero() {
+            return None;
+        }
+
+        let (q, r) = divmod_limbs(&self.limbs, &other.limbs);
+
+        Some((
+            BigInt::from_parts(self.negative != other.negative, q),
+            BigInt::from_parts(self.negative, r),
+        ))
+    }
+
+    /// The number of bits in the magnitude; zero for zero.
+    pub fn bits(&self) -> u64 {
+        match self.limbs.last() {
+            Some(top) => (self.limbs.len() as u64 - 1) * 32 + (32 - top.leading_zeros()) as u64,
+            None => 0,
+        }
+    }
+
+    /// Raises to a power, or fails with an `Overflow` error when the result would
+    /// need more than `MAX_POW_BITS` bits.
+    pub fn pow(&self, mut exp: u64) -> Result<BigInt, Error> {
+        if self.bits().saturating_sub(1).saturating_mul(exp) > MAX_POW_BITS {
+            return Err(Error::runtime(format!(
+                "Exponent {} is too large: the exact result would need more than {} bits", exp, MAX_POW_BITS
+            )).with_code(ErrorCode::Overflow));
+        }
+
+        let mut base = self.clone();
+        let mut res = BigInt::one();
+
+        while exp > 0 {
+            if exp & 1 == 1 {
+                res = &res * &base;
+            }
+
+            exp >>= 1;
+
+            if exp > 0 {
+                base = &base * &base;
+            }
+        }
+
+        Ok(res)
+    }
+
+    pub fn gcd(&self, other: &BigInt) -> BigInt {
+        let (mut a, mut b) = (self.abs(), other.abs());
+
+        while let Some((_, r)) = a.div_rem(&b) {
+            a = b;
+            b = r;
+        }
+
+        a
+    }
+
+    /// Parses unsigned digits in base 2 to 16, or returns `None` on an invalid digit.
+    pub fn parse_radix(digits: &str, radix: u32) -> Option<BigInt> {
+        if digits.is_empty() {
+            return None;
+        }
+
+        let mut limbs = Vec::new();
+
+        for c in digits.chars() {
+            limbs = add_limbs(&mul_limbs(&limbs, &[radix]), &[c.to_digit(radix)?]);
+            trim(&mut limbs);
+        }
+
+        Some(BigInt::from_parts(false, limbs))
+    }
+}
+
+impl From<i64> for BigInt {
+    fn from(value: i64) -> Self {
+        let magnitude = value.unsigned_abs();
+
+        BigInt::from_parts(value < 0, vec![magnitude as u32, (magnitude >> 32) as u32])
+    }
+}
+
+impl FromStr for BigInt {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (negative, digits) = match s.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, s),
+        };
+
+        if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(Error::parser(format!("Invalid integer '{}'", s)));
+        }
+
+        let mut limbs = Vec::new();
+
+        // nine decimal digits at a time fit in one limb
+        for chunk in digits.as_bytes().chunks(9) {
+            let value = chunk.iter().fold(0u32, |acc, b| acc * 10 + (b - b'0') as u32);
+
+            limbs = add_limbs(&mul_limbs(&limbs, &[10u32.pow(chunk.len() as u32)]), &[value]);
+            trim(&mut limbs);
+        }
+
+        Ok(BigInt::from_parts(negative, limbs))
+    }
+}
+
+impl Ord for BigInt {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self.negative, other.negative) {
+            (false, true) => Ordering::Greater,
+            (true, false) => Ordering::Less,
+            (false, false) => cmp_limbs(&self.limbs, &other.limbs),
+            (true, true) => cmp_limbs(&other.limbs, &self.limbs),
+        }
+    }
+}
+
+impl PartialOrd for BigInt {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl ops::Neg for BigInt {
+    type Output = BigInt;
+
+    fn neg(self) -> Self::Output {
+        BigInt::from_parts(!self.negative, self.limbs)
+    }
+}
+
+impl ops::Add<&BigInt> for &BigInt {
+    type Output = BigInt;
+
+    fn add(self, other: &BigInt) -> Self::Output {
+        if self.negative == other.negative {
+            return BigInt::from_parts(self.negative, add_limbs(&self.limbs, &other.limbs));
+        }
+
+        match cmp_limbs(&self.limbs, &other.limbs) {
+            Ordering::Less => BigInt::from_parts(other.negative, sub_limbs(&other.limbs, &self.limbs)),
+            _ => BigInt::from_parts(self.negative, sub_limbs(&self.limbs, &other.limbs)),
+        }
+    }
+}
+
+impl ops::Sub<&BigInt> for &BigInt {
+    type Output = BigInt;
+
+    fn sub(self, other: &BigInt) -> Self::Output {
+        self + &-other.clone()
+    }
+}
+
+impl ops::Mul<&BigInt> for &BigInt {
+    type Output = BigInt;
+
+    fn mul(self, other: &BigInt) -> Self::Output {
+        BigInt::from_parts(self.negative != other.negative, mul_limbs(&self.limbs, &other.limbs))
+    }
+}
+
+impl fmt::Display for BigInt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_zero() {
+            return write!(f, "0");
+        }
+
+        let mut chunks = Vec::new();
+        let mut rest = self.limbs.clone();
+
+        while !rest.is_empty() {
+            let (q, r) = divmod_limbs(&rest, &[1_000_000_000]);
+
+            chunks.push(r.first().copied().unwrap_or(0));
+            rest = q;
+        }
+
+        if self.negative {
+            write!(f, "-")?;
+        }
+
+        write!(f, "{}", chunks.pop().unwrap_or(0))?;
+
+        for chunk in chunks.iter().rev() {
+            write!(f, "{:09}", chunk)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn big(text: &str) -> BigInt {
+        text.parse().unwrap()
+    }
+
+    #[test]
+    fn division_truncates_towards_zero() {
+        let cases = [("7", "2", "3", "1"), ("-7", "2", "-3", "-1"), ("7", "-2", "-3", "1"), ("-7", "-2", "3", "-1")];
+
+        for (a, b, q, r) in cases {
+            assert_eq!(big(a).div_rem(&big(b)), Some((big(q), big(r))), "{} / {}", a, b);
+        }
+
+        assert_eq!(big("5").div_rem(&BigInt::zero()), None);
+    }
+
+    #[test]
+    fn multi_limb_arithmetic() {
+        let a = big("-123456789012345678901234567890");
+        let b = big("987654321987654321");
+
+        assert_eq!((&a * &b).to_string(), "-121932631246761163237311385323609205901126352690");
+
+        let (q, r) = (&a * &b).div_rem(&b).unwrap();
+        assert_eq!(q, a);
+        assert!(r.is_zero());
+
+        assert_eq!((&a + &b).to_string(), "-123456789011358024579246913569");
+        assert_eq!(big("-12").gcd(&big("18")), big("6"));
+    }
+
+    #[test]
+    fn pow_is_bounded() {
+        assert_eq!(big("-2").pow(63).unwrap(), BigInt::from(i64::MIN));
+        assert_eq!(BigInt::one().pow(u64::MAX).unwrap(), BigInt::one());
+        assert_eq!(big("2").pow(100_000_000).unwrap_err().code(), ErrorCode::Overflow);
+    }
+}
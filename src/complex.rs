@@ -0,0 +1,215 @@
+use std::fmt;
+use std::ops;
+use crate::matrix::Entry;
+
+/// A complex number `re + im*i`.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Complex {
+    pub re: f64,
+    pub im: f64,
+}
+
+impl Complex {
+    pub fn new(re: f64, im: f64) -> Complex {
+        Complex { re, im }
+    }
+
+    pub fn conj(self) -> Complex {
+        Complex::new(self.re, -self.im)
+    }
+
+    /// The modulus `|z|`.
+    pub fn abs(self) -> f64 {
+        self.re.hypot(self.im)
+    }
+
+    /// The argument in `(-pi, pi]`.
+    pub fn arg(self) -> f64 {
+        self.im.atan2(self.re)
+    }
+
+    pub fn exp(self) -> Complex {
+        let scale = self.re.exp();
+
+        Complex::new(scale * self.im.cos(), scale * self.im.sin())
+    }
+
+    pub fn sin(self) -> Complex {
+        Complex::new(self.re.sin() * self.im.cosh(), self.re.cos() * self.im.sinh())
+    }
+
+    pub fn cos(self) -> Complex {
+        Complex::new(self.re.cos() * self.im.cosh(), -self.re.sin() * self.im.sinh())
+    }
+
+    pub fn tan(self) -> Complex {
+        self.sin() / self.cos()
+    }
+
+    pub fn is_nan(self) -> bool {
+        self.re.is_nan() || self.im.is_nan()
+    }
+
+    /// The principal natural logarithm.
+    pub fn ln(self) -> Complex {
+        Complex::new(self.abs().ln(), self.arg())
+    }
+
+    /// The principal square root.
+    pub fn sqrt(self) -> Complex {
+        let r = self.abs();
+        let re = ((r + self.re) / 2.0).sqrt();
+        let im = ((r - self.re) / 2.0).sqrt();
+
+        Complex::new(re, if self.im < 0.0 { -im } else { im })
+    }
+
+    pub fn powi(self, exp: i32) -> Complex {
+        let mut base = if exp < 0 { Complex::new(1.0, 0.0) / self } else { self };
+        let mut exp = exp.unsigned_abs();
+        let mut res = Complex::new(1.0, 0.0);
+
+        while exp > 0 {
+            if exp & 1 == 1 {
+                res = res * base;
+            }
+
+            exp >>= 1;
+
+            if exp > 0 {
+                base = base * base;
+            }
+        }
+
+        res
+    }
+
+    /// The principal value of `self^exp`, exact for small integer exponents.
+    pub fn pow(self, exp: Complex) -> Complex {
+        if exp.im == 0.0 && exp.re.fract() == 0.0 && exp.re.abs() <= i32::MAX as f64 {
+            return self.powi(exp.re as i32);
+        }
+
+        if self == Complex::default() {
+            return Complex::default();
+        }
+
+        (self.ln() * exp).exp()
+    }
+}
+
+impl From<f64> for Complex {
+    fn from(re: f64) -> Self {
+        Complex::new(re, 0.0)
+    }
+}
+
+impl ops::Neg for Complex {
+    type Output = Complex;
+
+    fn neg(self) -> Self::Output {
+        Complex::new(-self.re, -self.im)
+    }
+}
+
+impl ops::Add for Complex {
+    type Output = Complex;
+
+    fn add(self, rhs: Complex) -> Self::Output {
+        Complex::new(self.re + rhs.re, self.im + rhs.im)
+    }
+}
+
+impl ops::Sub for Complex {
+    type Output = Complex;
+
+    fn sub(self, rhs: Complex) -> Self::Output {
+        Complex::new(self.re - rhs.re, self.im - rhs.im)
+    }
+}
+
+impl ops::Mul for Complex {
+    type Output = Complex;
+
+    fn mul(self, rhs: Complex) -> Self::Output {
+        Complex::new(self.re * rhs.re - self.im * rhs.im, self.re * rhs.im + self.im * rhs.re)
+    }
+}
+
+impl ops::Div for Complex {
+    type Output = Complex;
+
+    fn div(self, rhs: Complex) -> Self::Output {
+        let denom = rhs.re * rhs.re + rhs.im * rhs.im;
+
+        Complex::new(
+            (self.re * rhs.re + self.im * rhs.im) / denom,
+            (self.im * rhs.re - self.re * rhs.im) / denom,
+        )
+    }
+}
+
+impl Entry for Complex {
+    fn try_add(self, other: Self) -> Option<Self> {
+        Some(self + other)
+    }
+
+    fn try_mul(self, other: Self) -> Option<Self> {
+        Some(self * other)
+    }
+}
+
+impl fmt::Display for Complex {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.im.is_sign_negative() {
+            write!(f, "{}-{}i", self.re, -self.im)
+        } else {
+            write!(f, "{}+{}i", self.re, self.im)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f64::consts::{FRAC_PI_2, PI};
+
+    fn assert_close(a: Complex, b: Complex) {
+        assert!((a - b).abs() < 1e-12, "{} != {}", a, b);
+    }
+
+    #[test]
+    fn arithmetic_follows_the_usual_rules() {
+        let a = Complex::new(3.0, 4.0);
+        let b = Complex::new(1.0, -2.0);
+
+        assert_eq!(a + b, Complex::new(4.0, 2.0));
+        assert_eq!(a - b, Complex::new(2.0, 6.0));
+        assert_eq!(a * b, Complex::new(11.0, -2.0));
+        assert_close(a / b, Complex::new(-1.0, 2.0));
+        assert_eq!(-a, Complex::new(-3.0, -4.0));
+        assert_eq!(a.conj(), Complex::new(3.0, -4.0));
+        assert_eq!(a.abs(), 5.0);
+        assert_eq!(Complex::new(0.0, 1.0).arg(), FRAC_PI_2);
+    }
+
+    #[test]
+    fn functions_give_principal_values() {
+        let i = Complex::new(0.0, 1.0);
+
+        assert_close(Complex::from(-4.0).sqrt(), Complex::new(0.0, 2.0));
+        assert_close(Complex::from(-1.0).ln(), Complex::new(0.0, PI));
+        assert_close((i * Complex::from(PI)).exp(), Complex::from(-1.0));
+        assert_close(i.powi(3), Complex::new(0.0, -1.0));
+        assert_close(i.powi(-1), Complex::new(0.0, -1.0));
+        assert_close(i.pow(i), Complex::from((-FRAC_PI_2).exp()));
+    }
+
+    #[test]
+    fn display_shows_the_sign_of_the_imaginary_part_once() {
+        assert_eq!(Complex::new(3.0, 4.0).to_string(), "3+4i");
+        assert_eq!(Complex::new(3.0, -4.0).to_string(), "3-4i");
+        assert_eq!(Complex::new(0.0, -0.0).to_string(), "0-0i");
+        assert_eq!(Complex::new(-1.5, 0.0).to_string(), "-1.5+0i");
+    }
+}
@@ -0,0 +1,120 @@
+use std::fmt::Write;
+use crate::error::Error;
+
+const RED: &str = "\x1b[1;31m";
+const BLUE: &str = "\x1b[1;34m";
+const CYAN: &str = "\x1b[36m";
+const RESET: &str = "\x1b[0m";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Style {
+    /// Colored output for terminals.
+    Ansi,
+    /// No escape sequences, suitable for log files.
+    Plain,
+}
+
+impl Style {
+    fn paint(&self, color: &str, text: &str) -> String {
+        match self {
+            Style::Ansi => format!("{}{}{}", color, text, RESET),
+            Style::Plain => String::from(text),
+        }
+    }
+}
+
+/// Renders an error with its code, the offending source line and a caret under
+/// the failing range, followed by a hint when one is known for the error code.
+///
+/// `source` must be the text the error's span was computed against; if the span
+/// does not fit inside it the snippet is omitted.
+pub fn render(error: &Error, source: &str, style: Style) -> String {
+    let mut out = String::new();
+    let header = format!("{}[{}]", error.name(), error.code());
+
+    let _ = writeln!(out, "{}: {}", style.paint(RED, &header), error.message());
+
+    if let Some(span) = error.span().filter(|span| span.start <= source.len()) {
+        let line_start = source[..span.start].rfind('\n').map_or(0, |i| i + 1);
+        let line_end = source[span.start..].find('\n').map_or(source.len(), |i| span.start + i);
+        let line = &source[line_start..line_end];
+
+        let number = span.line.to_string();
+        let gutter = " ".repeat(number.len());
+
+        // keep tabs so the caret lines up with the offending text
+        let padding: String = source[line_start..span.start]
+            .chars()
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+        let width = source[span.start..span.end.clamp(span.start, line_end)].chars().count().max(1);
+
+        let _ = writeln!(out, "{}{} {}", gutter, style.paint(BLUE, "-->"), span);
+        let _ = writeln!(out, "{} {}", gutter, style.paint(BLUE, "|"));
+        let _ = writeln!(out, "{} {} {}", style.paint(BLUE, &number), style.paint(BLUE, "|"), line);
+        let _ = writeln!(out, "{} {} {}{}", gutter, style.paint(BLUE, "|"), padding, style.paint(RED, &"^".repeat(width)));
+    }
+
+    if let Some(hint) = error.code().hint() {
+        let gutter = " ".repeat(error.span().map_or(0, |span| span.line.to_string().len()));
+
+        let _ = writeln!(out, "{} {} {}", gutter, style.paint(CYAN, "= hint:"), hint);
+    }
+
+    out.truncate(out.trim_end_matches('\n').len());
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ErrorCode;
+    use crate::span::Span;
+
+    fn undefined(span: Span) -> Error {
+        Error::runtime("undefined variable 'y'").with_code(ErrorCode::UndefinedVariable).at(span)
+    }
+
+    #[test]
+    fn plain_rendering_underlines_the_span() {
+        let source = "x = 1;\nz = x + y";
+
+        assert_eq!(render(&undefined(Span::new(15, 16, 2, 9)), source, Style::Plain), [
+            "RuntimeError[E0203]: undefined variable 'y'",
+            " --> 2:9",
+            "  |",
+            "2 | z = x + y",
+            "  |         ^",
+            "  = hint: variables must be assigned before they are used",
+        ].join("\n"));
+    }
+
+    #[test]
+    fn carets_follow_tabs_and_cover_the_whole_span() {
+        let err = Error::runtime("bad").at(Span::new(3, 8, 1, 4));
+
+        assert_eq!(render(&err, "\ta = y + 1", Style::Plain), [
+            "RuntimeError[E0200]: bad",
+            " --> 1:4",
+            "  |",
+            "1 | \ta = y + 1",
+            "  | \t  ^^^^^",
+        ].join("\n"));
+    }
+
+    #[test]
+    fn spans_outside_the_source_only_show_the_message() {
+        let rendered = render(&undefined(Span::new(40, 41, 3, 1)), "y", Style::Plain);
+
+        assert_eq!(rendered, "RuntimeError[E0203]: undefined variable 'y'\n  = hint: variables must be assigned before they are used");
+    }
+
+    #[test]
+    fn ansi_rendering_adds_colors() {
+        let rendered = render(&undefined(Span::new(0, 1, 1, 1)), "y", Style::Ansi);
+
+        assert!(rendered.starts_with("\x1b[1;31mRuntimeError[E0203]\x1b[0m: undefined variable 'y'"));
+        assert!(rendered.contains("\x1b[1;31m^\x1b[0m"));
+    }
+}
@@ -1,18 +1,160 @@
 use std::fmt;
+use crate::span::Span;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorCode {
+    InvalidSyntax,
+    UnexpectedToken,
+    UnexpectedEnd,
+    Runtime,
+    TypeMismatch,
+    ArityMismatch,
+    UndefinedVariable,
+    UndefinedFunction,
+    NotCallable,
+    CallDepthExceeded,
+    UnknownType,
+    InvalidOperation,
+    ShapeMismatch,
+    IndexOutOfBounds,
+    SingularMatrix,
+    NoConvergence,
+    Overflow,
+    Domain,
+}
+
+impl ErrorCode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ErrorCode::InvalidSyntax => "E0100",
+            ErrorCode::UnexpectedToken => "E0101",
+            ErrorCode::UnexpectedEnd => "E0102",
+            ErrorCode::Runtime => "E0200",
+            ErrorCode::TypeMismatch => "E0201",
+            ErrorCode::ArityMismatch => "E0202",
+            ErrorCode::UndefinedVariable => "E0203",
+            ErrorCode::UndefinedFunction => "E0204",
+            ErrorCode::NotCallable => "E0205",
+            ErrorCode::CallDepthExceeded => "E0206",
+            ErrorCode::UnknownType => "E0207",
+            ErrorCode::InvalidOperation => "E0208",
+            ErrorCode::ShapeMismatch => "E0209",
+            ErrorCode::IndexOutOfBounds => "E0210",
+            ErrorCode::SingularMatrix => "E0211",
+            ErrorCode::NoConvergence => "E0212",
+            ErrorCode::Overflow => "E0213",
+            ErrorCode::Domain => "E0214",
+        }
+    }
+
+    pub fn hint(&self) -> Option<&'static str> {
+        match self {
+            ErrorCode::UnexpectedEnd => Some("the input ended early; check for unclosed brackets"),
+            ErrorCode::ArityMismatch => Some("check the number of arguments in the function definition"),
+            ErrorCode::UndefinedVariable => Some("variables must be assigned before they are used"),
+            ErrorCode::UndefinedFunction => Some("functions must be declared with `fn` before they are called"),
+            ErrorCode::CallDepthExceeded => Some("check for unbounded recursion"),
+            ErrorCode::UnknownType => Some("parameter types are Number, Decimal, BigInt, Rational, Complex, Bool, String, Array, Matrix or Function"),
+            ErrorCode::ShapeMismatch => Some("check the dimensions of the operands"),
+            ErrorCode::IndexOutOfBounds => Some("indices start at 0 and slice ends are exclusive"),
+            ErrorCode::SingularMatrix => Some("the rows of the matrix are linearly dependent"),
+            ErrorCode::NoConvergence => Some("the iteration cap was reached; try passing a larger tolerance or iteration cap, as in `eig(M, 1e-9, 5000)`"),
+            ErrorCode::Overflow => Some("Number results must fit in 64 bits and exact powers in 131072 bits; use a Decimal operand or enable overflow promotion (`overflow promote` in the REPL)"),
+            ErrorCode::Domain => Some("the argument is outside the function's domain; permissive mode returns NaN instead"),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
 
 #[derive(Debug)]
 pub enum Error {
-    ParserError(String),
-    RuntimeError(String),
+    ParserError {
+        message: String,
+        code: ErrorCode,
+        span: Option<Span>,
+    },
+    RuntimeError {
+        message: String,
+        code: ErrorCode,
+        span: Option<Span>,
+    },
+}
+
+impl Error {
+    pub fn parser(message: impl Into<String>) -> Error {
+        Error::ParserError {
+            message: message.into(),
+            code: ErrorCode::InvalidSyntax,
+            span: None,
+        }
+    }
+
+    pub fn runtime(message: impl Into<String>) -> Error {
+        Error::RuntimeError {
+            message: message.into(),
+            code: ErrorCode::Runtime,
+            span: None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Error::ParserError { .. } => "ParserError",
+            Error::RuntimeError { .. } => "RuntimeError",
+        }
+    }
+
+    pub fn message(&self) -> &str {
+        match self {
+            Error::ParserError { message, .. } | Error::RuntimeError { message, .. } => message,
+        }
+    }
+
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            Error::ParserError { code, .. } | Error::RuntimeError { code, .. } => *code,
+        }
+    }
+
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            Error::ParserError { span, .. } | Error::RuntimeError { span, .. } => *span,
+        }
+    }
+
+    pub fn with_code(mut self, value: ErrorCode) -> Error {
+        match &mut self {
+            Error::ParserError { code, .. } | Error::RuntimeError { code, .. } => *code = value,
+        }
+
+        self
+    }
+
+    /// Attaches a location to the error, unless a more precise one was already set.
+    pub fn at(mut self, location: Span) -> Error {
+        match &mut self {
+            Error::ParserError { span, .. } | Error::RuntimeError { span, .. } => {
+                span.get_or_insert(location);
+            },
+        }
+
+        self
+    }
 }
 
 impl std::error::Error for Error {}
 
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            Error::ParserError(msg) => write!(f, "ParserError: {}", *msg),
-            Error::RuntimeError(msg) => write!(f, "RuntimeError: {}", *msg)
+        match self.span() {
+            Some(span) => write!(f, "{}[{}] at {}: {}", self.name(), self.code(), span, self.message()),
+            None => write!(f, "{}[{}]: {}", self.name(), self.code(), self.message()),
         }
     }
-}
\ No newline at end of file
+}
@@ -1,56 +1,115 @@
 use std::ops::Deref;
-use crate::ast::{Node, Operator};
-use crate::error::Error;
-use crate::memory::{ActivationRecord, CallStack, Value};
+use std::rc::Rc;
+use crate::ast::{Block, Index, Node, NodeKind, Operator};
+use crate::complex::Complex;
+use crate::error::{Error, ErrorCode};
+use crate::linalg;
+use crate::math::{self, Domain};
+use crate::stats;
+use crate::strings;
+use crate::matrix::Matrix;
+use crate::memory::{ActivationRecord, CallStack, Function, Parameter, RecordKind, Selector, Value};
+use crate::symbol::SymbolTable;
+
+const MAX_CALL_DEPTH: usize = 256;
+
+/// When less than this much stack is left, `visit` continues on a fresh segment of
+/// `STACK_SEGMENT` bytes, so deep recursion does not depend on the thread's stack size.
+const RED_ZONE: usize = 256 * 1024;
+const STACK_SEGMENT: usize = 4 * 1024 * 1024;
+
+/// What happens when `Number` arithmetic overflows 64 bits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Overflow {
+    /// Raise a runtime error.
+    #[default]
+    Checked,
+    /// Redo the operation with the operands converted to `Decimal`.
+    Promote,
+    /// Redo the operation with the operands converted to `BigInt`. Integer matrices
+    /// cannot hold `BigInt` entries and are converted to `Decimal` matrices instead.
+    Widen,
+}
 
 pub struct Interpreter {
     stack: CallStack,
+    symbols: SymbolTable<'static>,
+    overflow: Overflow,
+    exact: bool,
+    domain: Domain,
 }
 
 fn matrix_transpose(args: Vec<Value>) -> Result<Value, Error> {
-    let mat_value_opt = args.first();
-
-    if mat_value_opt.is_none() {
-        return Result::Err(Interpreter::error("Expected a matrix"));
+    match args.first() {
+        Some(Value::Matrix(mat)) => Result::Ok(Value::Matrix(mat.transpose())),
+        _ => Result::Err(Interpreter::error("Expected a matrix").with_code(ErrorCode::TypeMismatch)),
     }
+}
 
-    match mat_value_opt.unwrap() {
-        Value::Array(mat) => {
-            let rows = mat.len();
-            let mut cols = 0;
-
-            if rows > 0 {
-                cols = mat.iter().next().unwrap().as_array().len();
-            }
-
-            let mut res: Vec<Value> = Vec::with_capacity(cols);
-
-            for i in 0..cols {
-                let row = Vec::with_capacity(rows);
-
-                res.push(Value::Array(row));
-            }
-
-            for i in 0..rows {
-                let row_vec = mat[i].as_array();
-
-                for j in 0..cols {
-                    let res_vec = res[j].as_array_mut();
-
-                    res_vec.insert(i, row_vec[j].clone());
-                }
-            }
-
-            Result::Ok(Value::Array(res))
-        },
-        _ => Result::Err(Interpreter::error("Expected a matrix")),
+fn to_float(args: Vec<Value>) -> Result<Value, Error> {
+    linalg::expect_arity("float", &args, 1)?;
+
+    match args.into_iter().next() {
+        Some(value @ (Value::Array(_) | Value::Matrix(_))) => Ok(value.promote()),
+        Some(Value::Complex(_)) => Err(Error::runtime("float: cannot convert a Complex; use re or abs")
+            .with_code(ErrorCode::TypeMismatch)),
+        Some(value) if value.is_scalar() => Ok(value.promote()),
+        Some(other) => Err(Error::runtime(format!("float: expected a number, got {}", other.kind()))
+            .with_code(ErrorCode::TypeMismatch)),
+        None => unreachable!(),
     }
 }
 
 pub fn add_natives(mut ar: ActivationRecord) -> ActivationRecord {
     ar.insert(String::from("trn"), Value::NativeFunction(matrix_transpose));
-
-    return ar;
+    ar.insert(String::from("float"), Value::NativeFunction(to_float));
+    ar.insert(String::from("re"), Value::NativeFunction(math::re));
+    ar.insert(String::from("im"), Value::NativeFunction(math::im));
+    ar.insert(String::from("abs"), Value::NativeFunction(math::abs));
+    ar.insert(String::from("arg"), Value::NativeFunction(math::arg));
+    ar.insert(String::from("conj"), Value::NativeFunction(math::conj));
+    ar.insert(String::from("sin"), Value::NativeFunction(math::sin));
+    ar.insert(String::from("cos"), Value::NativeFunction(math::cos));
+    ar.insert(String::from("tan"), Value::NativeFunction(math::tan));
+    ar.insert(String::from("exp"), Value::NativeFunction(math::exp));
+    ar.insert(String::from("ln"), Value::NativeFunction(math::ln));
+    ar.insert(String::from("log10"), Value::NativeFunction(math::log10));
+    ar.insert(String::from("sqrt"), Value::NativeFunction(math::sqrt));
+    ar.insert(String::from("floor"), Value::NativeFunction(math::floor));
+    ar.insert(String::from("ceil"), Value::NativeFunction(math::ceil));
+    ar.insert(String::from("round"), Value::NativeFunction(math::round));
+    ar.insert(String::from("min"), Value::NativeFunction(math::min));
+    ar.insert(String::from("max"), Value::NativeFunction(math::max));
+    ar.insert(String::from("mod"), Value::NativeFunction(math::modulus));
+    ar.insert(String::from("sum"), Value::NativeFunction(stats::sum));
+    ar.insert(String::from("prod"), Value::NativeFunction(stats::prod));
+    ar.insert(String::from("mean"), Value::NativeFunction(stats::mean));
+    ar.insert(String::from("median"), Value::NativeFunction(stats::median));
+    ar.insert(String::from("var"), Value::NativeFunction(stats::var));
+    ar.insert(String::from("std"), Value::NativeFunction(stats::std));
+    ar.insert(String::from("argmin"), Value::NativeFunction(stats::argmin));
+    ar.insert(String::from("argmax"), Value::NativeFunction(stats::argmax));
+    ar.insert(String::from("cumsum"), Value::NativeFunction(stats::cumsum));
+    ar.insert(String::from("cumprod"), Value::NativeFunction(stats::cumprod));
+    ar.insert(String::from("len"), Value::NativeFunction(strings::len));
+    ar.insert(String::from("str"), Value::NativeFunction(strings::str));
+    ar.insert(String::from("format"), Value::NativeFunction(strings::format));
+    math::add_constants(&mut ar);
+    ar.insert(String::from("det"), Value::NativeFunction(linalg::det));
+    ar.insert(String::from("inv"), Value::NativeFunction(linalg::inv));
+    ar.insert(String::from("solve"), Value::NativeFunction(linalg::solve));
+    ar.insert(String::from("rank"), Value::NativeFunction(linalg::rank));
+    ar.insert(String::from("trace"), Value::NativeFunction(linalg::trace));
+    ar.insert(String::from("eye"), Value::NativeFunction(linalg::eye));
+    ar.insert(String::from("zeros"), Value::NativeFunction(linalg::zeros));
+    ar.insert(String::from("ones"), Value::NativeFunction(linalg::ones));
+    ar.insert(String::from("lu"), Value::NativeFunction(linalg::lu));
+    ar.insert(String::from("qr"), Value::NativeFunction(linalg::qr));
+    ar.insert(String::from("chol"), Value::NativeFunction(linalg::chol));
+    ar.insert(String::from("eig"), Value::NativeFunction(linalg::eig));
+    ar.insert(String::from("svd"), Value::NativeFunction(linalg::svd));
+
+    ar
 }
 
 pub trait WithNatives {
@@ -63,111 +122,598 @@ impl WithNatives for ActivationRecord {
     }
 }
 
+impl Default for Interpreter {
+    fn default() -> Self {
+        Interpreter::new()
+    }
+}
+
 impl Interpreter {
     pub fn new() -> Interpreter {
-        Interpreter {
-            stack: CallStack::from_record(
-                ActivationRecord::with_natives()
-            ),
-        }
+        Interpreter::from_record(ActivationRecord::with_natives())
     }
 
     pub fn from_record(record: ActivationRecord) -> Interpreter {
-        Interpreter {
-            stack: CallStack::from_record(record),
-        }
+        Interpreter::from_stack(CallStack::from_record(record))
     }
 
     pub fn from_stack(stack: CallStack) -> Interpreter {
         Interpreter {
             stack,
+            symbols: SymbolTable::new(),
+            overflow: Overflow::default(),
+            exact: false,
+            domain: Domain::default(),
         }
     }
 
+    pub fn overflow(&self) -> Overflow {
+        self.overflow
+    }
+
+    pub fn set_overflow(&mut self, overflow: Overflow) {
+        self.overflow = overflow;
+    }
+
+    pub fn exact(&self) -> bool {
+        self.exact
+    }
+
+    /// In exact mode, dividing integers gives a `Rational` instead of a `Decimal`.
+    pub fn set_exact(&mut self, exact: bool) {
+        self.exact = exact;
+    }
+
+    pub fn domain(&self) -> Domain {
+        self.domain
+    }
+
+    /// In permissive mode, math natives return NaN for arguments outside their domain.
+    pub fn set_domain(&mut self, domain: Domain) {
+        self.domain = domain;
+    }
+
+    /// The value bound to `name` in the innermost scope that defines it.
+    pub fn lookup(&self, name: &str) -> Option<&Value> {
+        self.stack.lookup(name)
+    }
+
     pub(crate) fn error(msg: &str) -> Error {
-        Error::RuntimeError(String::from(msg))
+        Error::runtime(msg)
     }
 }
 
 impl Interpreter {
+    fn define_function(&mut self, name: &str, parameters: &[Node], body: &Block, doc: &Option<String>) -> Result<Value, Error> {
+        let mut params = Vec::with_capacity(parameters.len());
+
+        for node in parameters.iter() {
+            match &node.kind {
+                NodeKind::Declaration { name, kind } => {
+                    if self.symbols.lookup(kind).is_none() {
+                        return Err(Error::runtime(format!("Unknown type {}", kind))
+                            .with_code(ErrorCode::UnknownType)
+                            .at(node.span));
+                    }
+
+                    params.push(Parameter {
+                        name: name.clone(),
+                        kind: kind.clone(),
+                    });
+                },
+                _ => return Err(Interpreter::error("Invalid function parameter").at(node.span)),
+            }
+        }
+
+        let function = Value::Function(Rc::new(Function {
+            name: String::from(name),
+            parameters: params,
+            body: body.clone(),
+            doc: doc.clone(),
+        }));
+
+        let ar = self.stack.peek_mut().unwrap();
+
+        ar.insert(String::from(name), function.clone());
+
+        Ok(function)
+    }
+
+    fn bind_argument(param: &Parameter, arg: Value) -> Result<Value, Error> {
+        match (param.kind.as_str(), arg) {
+            ("Decimal", Value::Number(value)) => Ok(Value::Decimal(value as f64)),
+            ("Decimal", value) if value.is_exact() => Ok(value.promote()),
+            ("Number" | "Rational", value @ Value::BigInt(_)) => Ok(value),
+            ("BigInt" | "Rational", value @ Value::Number(_)) => Ok(value),
+            ("Complex", value) if value.is_scalar() => Ok(Value::Complex(value.as_complex().unwrap_or_default())),
+            ("Array", Value::Matrix(mat)) => Ok(Value::Matrix(mat)),
+            (kind, value) if kind == value.kind() => Ok(value),
+            (kind, value) => Err(Error::runtime(format!(
+                "Expected {} for parameter {}, got {}", kind, param.name, value.kind()
+            )).with_code(ErrorCode::TypeMismatch)),
+        }
+    }
+
+    fn call_function(&mut self, function: &Function, args: Vec<Value>) -> Result<Value, Error> {
+        if args.len() != function.parameters.len() {
+            return Err(Error::runtime(format!(
+                "{} expects {} argument(s), got {}", function.name, function.parameters.len(), args.len()
+            )).with_code(ErrorCode::ArityMismatch));
+        }
+
+        if self.stack.depth() >= MAX_CALL_DEPTH {
+            return Err(Interpreter::error("Maximum call depth exceeded").with_code(ErrorCode::CallDepthExceeded));
+        }
+
+        let mut ar = ActivationRecord::with_kind(RecordKind::Function);
+
+        for (param, arg) in function.parameters.iter().zip(args) {
+            ar.insert(param.name.clone(), Interpreter::bind_argument(param, arg)?);
+        }
+
+        self.stack.push(ar);
+
+        let res = self.visit_block(&function.body);
+
+        self.stack.pop();
+
+        res
+    }
+
+    /// Evaluates `f`, retrying it with every `Number` argument promoted to `Decimal`
+    /// or widened to `BigInt` if it overflowed, depending on the overflow mode.
+    fn with_overflow(&self, args: Vec<Value>, f: impl Fn(Vec<Value>) -> Result<Value, Error>) -> Result<Value, Error> {
+        let convert = match self.overflow {
+            Overflow::Checked => return f(args),
+            Overflow::Promote => Value::promote,
+            Overflow::Widen => Value::widen,
+        };
+
+        match f(args.clone()) {
+            Err(err) if err.code() == ErrorCode::Overflow => f(args.into_iter().map(convert).collect()),
+            res => res,
+        }
+    }
+
+    fn visit_block(&mut self, block: &Block) -> Result<Value, Error> {
+        let mut value = Value::Number(0);
+
+        for node in block.0.iter() {
+            value = self.visit(node)?;
+        }
+
+        Ok(value)
+    }
+
+    /// Evaluates a block inside its own block record, which is popped afterwards.
+    fn visit_scoped(&mut self, block: &Block, ar: ActivationRecord) -> Result<Value, Error> {
+        self.stack.push(ar);
+
+        let res = self.visit_block(block);
+
+        self.stack.pop();
+
+        res
+    }
+
+    fn visit_call(&mut self, name: &str, arguments: &[Node]) -> Result<Value, Error> {
+        let mut args: Vec<Value> = Vec::new();
+
+        for node in arguments.iter() {
+            args.push(self.visit(node)?);
+        }
+
+        match self.stack.lookup(name) {
+            Some(Value::NativeFunction(fn_ref)) => {
+                let fn_ref = *fn_ref;
+
+                math::with_domain(self.domain, || self.with_overflow(args, fn_ref))
+            },
+            Some(Value::Function(function)) => {
+                let function = function.clone();
+
+                self.call_function(&function, args)
+            },
+            Some(_) => Result::Err(Interpreter::error("Invalid function").with_code(ErrorCode::NotCallable)),
+            None => Result::Err(Error::runtime(format!("Function '{}' not found", name))
+                .with_code(ErrorCode::UndefinedFunction)),
+        }
+    }
+
+    fn visit_binary_op(&mut self, op: &Operator, lhs: &Node, rhs: &Node) -> Result<Value, Error> {
+        let mut left = self.visit(lhs)?;
+        let mut right = self.visit(rhs)?;
+
+        if self.exact && matches!(op, Operator::Div | Operator::ElemDiv | Operator::Exp | Operator::ElemExp) {
+            left = left.exact();
+            right = right.exact();
+        }
+
+        self.with_overflow(vec![left, right], |mut args| {
+            let right = args.pop().unwrap();
+
+            args.pop().unwrap().apply(op, right)
+        })
+    }
+
+    fn visit_if(&mut self, condition: &Node, then: &Block, otherwise: Option<&Block>) -> Result<Value, Error> {
+        let branch = match self.visit(condition)? {
+            Value::Bool(true) => Some(then),
+            Value::Bool(false) => otherwise,
+            other => return Err(Error::runtime(format!("Expected a Bool condition, got {}", other.kind()))
+                .with_code(ErrorCode::TypeMismatch)
+                .at(condition.span)),
+        };
+
+        match branch {
+            Some(block) => self.visit_scoped(block, ActivationRecord::with_kind(RecordKind::Block)),
+            None => Ok(Value::Number(0)),
+        }
+    }
+
+    fn visit_while(&mut self, condition: &Node, body: &Block) -> Result<Value, Error> {
+        let mut value = Value::Number(0);
+
+        loop {
+            match self.visit(condition)? {
+                Value::Bool(true) => {},
+                Value::Bool(false) => break,
+                other => return Err(Error::runtime(format!("Expected a Bool condition, got {}", other.kind()))
+                    .with_code(ErrorCode::TypeMismatch)
+                    .at(condition.span)),
+            }
+
+            value = self.visit_scoped(body, ActivationRecord::with_kind(RecordKind::Block))?;
+        }
+
+        Ok(value)
+    }
+
+    fn visit_for(&mut self, variable: &str, iterable: &Node, body: &Block) -> Result<Value, Error> {
+        let mut value = Value::Number(0);
+
+        let items: Box<dyn Iterator<Item = Value>> = match &iterable.kind {
+            // iterate ranges lazily rather than materializing them as an array
+            NodeKind::BinaryOp { op: Operator::Range, lhs, rhs } => {
+                let (start, end) = self.visit_range(lhs, rhs)?;
+
+                Box::new((start..end).map(Value::Number))
+            },
+            _ => match self.visit(iterable)? {
+                Value::Array(arr) => Box::new(arr.into_iter()),
+                Value::Matrix(mat) => Box::new(mat.to_rows().into_iter()),
+                other => return Err(Error::runtime(format!("Cannot iterate over {}", other.kind()))
+                    .with_code(ErrorCode::TypeMismatch)
+                    .at(iterable.span)),
+            },
+        };
+
+        for item in items {
+            let mut ar = ActivationRecord::with_kind(RecordKind::Block);
+
+            ar.insert(String::from(variable), item);
+
+            value = self.visit_scoped(body, ar)?;
+        }
+
+        Ok(value)
+    }
+
+    fn visit_range(&mut self, lhs: &Node, rhs: &Node) -> Result<(i64, i64), Error> {
+        let mut bound = |node: &Node| match self.visit(node)? {
+            Value::Number(value) => Ok(value),
+            other => Err(Error::runtime(format!("Range bounds must be Numbers, got {}", other.kind()))
+                .with_code(ErrorCode::TypeMismatch)
+                .at(node.span)),
+        };
+
+        Ok((bound(lhs)?, bound(rhs)?))
+    }
+
+    fn visit_selectors(&mut self, indices: &[Index]) -> Result<Vec<Selector>, Error> {
+        let mut selectors = Vec::with_capacity(indices.len());
+
+        for index in indices.iter() {
+            let selector = match index {
+                Index::All => Selector::All,
+                Index::Expr(Node { kind: NodeKind::BinaryOp { op: Operator::Range, lhs, rhs }, .. }) => {
+                    let (start, end) = self.visit_range(lhs, rhs)?;
+
+                    Selector::Range(start, end)
+                },
+                Index::Expr(node) => match self.visit(node)? {
+                    Value::Number(i) => Selector::At(i),
+                    other => return Err(Error::runtime(format!("Indices must be Numbers, got {}", other.kind()))
+                        .with_code(ErrorCode::TypeMismatch)
+                        .at(node.span)),
+                },
+            };
+
+            selectors.push(selector);
+        }
+
+        Ok(selectors)
+    }
+
     pub fn visit(&mut self, node: &Node) -> Result<Value, Error> {
+        stacker::maybe_grow(RED_ZONE, STACK_SEGMENT, || self.visit_kind(&node.kind).map_err(|err| err.at(node.span)))
+    }
+
+    fn visit_kind(&mut self, node: &NodeKind) -> Result<Value, Error> {
         match node {
-            Node::Number(value) => Ok(Value::Number(value.clone())),
-            Node::Decimal(value) => Ok(Value::Decimal(value.clone())),
-            Node::Array(vec) => {
+            NodeKind::Number(value) => Ok(Value::Number(*value)),
+            NodeKind::Decimal(value) => Ok(Value::Decimal(*value)),
+            NodeKind::BigInt(value) => Ok(Value::BigInt(value.clone())),
+            NodeKind::Imaginary(value) => Ok(Value::Complex(Complex::new(0.0, *value))),
+            NodeKind::Bool(value) => Ok(Value::Bool(*value)),
+            NodeKind::String(value) => Ok(Value::String(value.clone())),
+            NodeKind::Array(vec) => {
                 let mut new_vec = Vec::new();
 
                 for item in vec.iter() {
                     new_vec.push(self.visit(item)?);
                 }
 
-                Ok(Value::Array(new_vec))
+                match Matrix::from_values(&new_vec) {
+                    Some(mat) => Ok(Value::Matrix(mat?)),
+                    None => Ok(Value::Array(new_vec)),
+                }
             },
-            Node::Variable(name) => {
-                let ar = self.stack.peek().unwrap();
-                let val = ar.get(&String::from(*name));
-
-                match val {
+            NodeKind::Variable(name) => {
+                match self.stack.lookup(name) {
                     Some(res) => Ok(res.clone()),
-                    None => Err(Interpreter::error("Undefined variable")),
+                    None => Err(Error::runtime(format!("Undefined variable '{}'", name))
+                        .with_code(ErrorCode::UndefinedVariable)),
                 }
             },
-            Node::Call { function: fn_name, arguments } => {
-                let mut args: Vec<Value> = Vec::new();
-
-                for node in arguments.iter() {
-                    args.push(self.visit(node)?);
-                }
-
-                let curr_ar = self.stack.peek().unwrap();
-                let fn_value = curr_ar.get(&String::from(*fn_name));
+            NodeKind::Call { function, arguments } => self.visit_call(function, arguments),
+            NodeKind::Assign { lhs, indices, rhs } if indices.is_empty() => {
+                let value = self.visit(rhs)?;
+                let res = value.clone();
 
-                if fn_value.is_none() {
-                    return Result::Err(Interpreter::error("Function not found"))
-                }
+                self.stack.assign(lhs.clone(), value);
 
-                match fn_value.unwrap() {
-                    Value::NativeFunction(fn_ref) => {
-                        Result::Ok(fn_ref(args)?)
-                    },
-                    _ => Result::Err(Interpreter::error("Invalid function")),
-                }
+                Ok(res)
             },
-            Node::Assign { lhs, rhs } => {
+            NodeKind::Assign { lhs, indices, rhs } => {
+                let selectors = self.visit_selectors(indices)?;
                 let value = self.visit(rhs)?;
-                let res = value.clone();
 
-                let ar = self.stack.peek_mut().unwrap();
+                match self.stack.lookup_mut(lhs) {
+                    Some(target) => target.assign_index(&selectors, value.clone())?,
+                    None => return Err(Error::runtime(format!("Undefined variable '{}'", lhs))
+                        .with_code(ErrorCode::UndefinedVariable)),
+                }
 
-                ar.insert( String::from(*lhs), value);
+                Ok(value)
+            },
+            NodeKind::Index { target, indices } => {
+                let value = self.visit(target)?;
+                let selectors = self.visit_selectors(indices)?;
 
-                Ok(res)
+                value.index(&selectors)
             },
-            Node::UnaryOp { op, rhs } => {
+            NodeKind::UnaryOp { op, rhs } => {
                 let right = self.visit(rhs.deref())?;
 
                 match op {
                     Operator::Add => Ok(right),
-                    Operator::Sub => Ok(-right),
+                    Operator::Sub => self.with_overflow(vec![right], |mut args| -args.remove(0)),
                     _ => Err(Interpreter::error("Invalid unary operation")),
                 }
             },
-            Node::BinaryOp { op, lhs, rhs } => {
-                let left = self.visit(lhs.deref())?;
-                let right = self.visit(rhs.deref())?;
-
-                let res = match op {
-                    Operator::Add => left + right,
-                    Operator::Sub => left - right,
-                    Operator::Mul => left * right,
-                    Operator::Div => left / right,
-                    Operator::Exp => left.pow(right),
-                };
+            NodeKind::BinaryOp { op: Operator::Range, lhs, rhs } => {
+                let (start, end) = self.visit_range(lhs, rhs)?;
 
-                Ok(res)
+                Ok(Value::Array((start..end).map(Value::Number).collect()))
             },
-            Node::Declaration { name, kind } => todo!("Unimplemented"),
-            Node::Function { name, parameters, body } => todo!("Unimplemented"),
+            NodeKind::BinaryOp { op, lhs, rhs } => self.visit_binary_op(op, lhs, rhs),
+            NodeKind::Declaration { .. } => Err(Interpreter::error("Declarations are only allowed as function parameters")),
+            NodeKind::Function { name, parameters, body, doc } => self.define_function(name, parameters, body, doc),
+            NodeKind::If { condition, then, otherwise } => self.visit_if(condition, then, otherwise.as_ref()),
+            NodeKind::While { condition, body } => self.visit_while(condition, body),
+            NodeKind::For { variable, iterable, body } => self.visit_for(variable, iterable, body),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::Lexer;
+    use crate::parser::Parser;
+    use crate::span::Span;
+
+    /// Runs a program and returns the value of its last statement.
+    fn eval_with(interpreter: &mut Interpreter, source: &str) -> Result<Value, Error> {
+        let mut lexer = Lexer::new(source);
+        let mut parser = Parser::new(&mut lexer);
+        let mut value = Value::Number(0);
+
+        for node in parser.run()? {
+            value = interpreter.visit(&node)?;
+        }
+
+        Ok(value)
+    }
+
+    fn eval(source: &str) -> Result<Value, Error> {
+        eval_with(&mut Interpreter::new(), source)
+    }
+
+    #[test]
+    fn functions_bind_arguments_and_return_the_last_value() {
+        assert_eq!(eval("fn f(x: Number) { x^2 + 1 }; f(3)").unwrap(), Value::Number(10));
+        assert_eq!(eval("fn add(a: Number, b: Number) { c = a + b; c * 2 }; add(1, 2)").unwrap(), Value::Number(6));
+        assert_eq!(eval("fn f(n: Number) { if n == 0 { 1 } else { n * f(n - 1) } }; f(5)").unwrap(), Value::Number(120));
+        assert_eq!(eval("fn twice(g: Function, x: Number) { g(g(x)) }; fn inc(x: Number) { x + 1 }; twice(inc, 1)").unwrap(), Value::Number(3));
+    }
+
+    #[test]
+    fn calls_get_their_own_record() {
+        assert_eq!(eval("x = 5; fn f(x: Number) { x = x * 2; x }; f(1); x").unwrap(), Value::Number(5));
+        assert_eq!(eval("fn f(x: Number) { y = x }; f(1); y").unwrap_err().code(), ErrorCode::UndefinedVariable);
+    }
+
+    #[test]
+    fn arguments_are_checked_against_parameters() {
+        assert_eq!(eval("fn f(x: Number) { x }; f(1, 2)").unwrap_err().code(), ErrorCode::ArityMismatch);
+        assert_eq!(eval("fn f(x: Number) { x }; f()").unwrap_err().code(), ErrorCode::ArityMismatch);
+        assert_eq!(eval("fn f(x: Number) { x }; f([1])").unwrap_err().code(), ErrorCode::TypeMismatch);
+        assert_eq!(eval("fn f(x: Shape) { x }").unwrap_err().code(), ErrorCode::UnknownType);
+        assert_eq!(eval("g(1)").unwrap_err().code(), ErrorCode::UndefinedFunction);
+        assert_eq!(eval("x = 1; x(2)").unwrap_err().code(), ErrorCode::NotCallable);
+    }
+
+    #[test]
+    fn assigning_a_native_name_in_a_function_keeps_the_native() {
+        let res = eval("fn g(n: Number) { sum = n; sum }; g(3); sum([1, 2, 3])");
+
+        assert_eq!(res.unwrap(), Value::Number(6));
+    }
+
+    #[test]
+    fn runtime_errors_point_at_the_failing_expression() {
+        let err = eval("x = 1;\ny = x + [1, 2] * z").unwrap_err();
+
+        assert_eq!(err.code(), ErrorCode::UndefinedVariable);
+        assert_eq!(err.span(), Some(Span::new(24, 25, 2, 18)));
+    }
+
+    #[test]
+    fn comparisons_give_bools() {
+        let text = |source| format!("{:#}", eval(source).unwrap());
+
+        assert_eq!(text("1 < 2"), "true");
+        assert_eq!(text("2 <= 2.0"), "true");
+        assert_eq!(text("3 > 4"), "false");
+        assert_eq!(text("1 == 1.0"), "true");
+        assert_eq!(text("1 + 1 != 2"), "false");
+        assert_eq!(text("\"a\" == \"a\""), "true");
+        assert_eq!(eval("1 < true").unwrap_err().code(), ErrorCode::InvalidOperation);
+        assert_eq!(eval("1 < 2 < 3").unwrap_err().code(), ErrorCode::InvalidOperation);
+    }
+
+    #[test]
+    fn if_is_an_expression() {
+        let relu = "fn relu(x: Number) { if x > 0 { x } else { 0 } };";
+
+        assert_eq!(eval(&format!("{} relu(-3)", relu)).unwrap(), Value::Number(0));
+        assert_eq!(eval(&format!("{} relu(4)", relu)).unwrap(), Value::Number(4));
+        assert_eq!(eval("x = if 1 < 2 { 10 } else { 20 }; x").unwrap(), Value::Number(10));
+        assert_eq!(eval("if 1 > 2 { 1 } else if 2 > 1 { 2 } else { 3 }").unwrap(), Value::Number(2));
+        assert_eq!(eval("if 1 > 2 { 5 }").unwrap(), Value::Number(0));
+        assert_eq!(eval("if 1 { 2 } else { 3 }").unwrap_err().code(), ErrorCode::TypeMismatch);
+    }
+
+    #[test]
+    fn call_depth_ignores_block_scopes() {
+        // test threads get the default 2MB stack, which the full depth must not overflow
+        let source = "fn f(n: Number) { if n == 0 { 0 } else { f(n - 1) + 1 } }; f(255)";
+
+        assert_eq!(eval(source).unwrap(), Value::Number(255));
+    }
+
+    #[test]
+    fn call_depth_is_limited() {
+        let source = "fn f(n: Number) { if n == 0 { 0 } else { f(n - 1) + 1 } }; f(256)";
+
+        assert_eq!(eval(source).unwrap_err().code(), ErrorCode::CallDepthExceeded);
+    }
+
+    #[test]
+    fn exact_mode_leaves_matrices_alone() {
+        let mut interpreter = Interpreter::new();
+
+        interpreter.set_exact(true);
+
+        let text = |interpreter: &mut Interpreter, source| format!("{:#}", eval_with(interpreter, source).unwrap());
+
+        assert_eq!(text(&mut interpreter, "[[1, 2], [3, 4]] / 2"), "[[0.5, 1], [1.5, 2]]");
+        assert_eq!(text(&mut interpreter, "[[1, 2], [3, 4]] ./ [[2, 2], [2, 2]]"), "[[0.5, 1], [1.5, 2]]");
+        assert_eq!(text(&mut interpreter, "[[1, 2], [3, 4]]^2"), "[[7, 10], [15, 22]]");
+        assert_eq!(text(&mut interpreter, "[1, 2] / 4"), "[1/4, 1/2]");
+    }
+
+    #[test]
+    fn integer_powers_stay_numbers() {
+        assert_eq!(eval("2^10").unwrap(), Value::Number(1024));
+        assert_eq!(eval("(-3)^3").unwrap(), Value::Number(-27));
+        assert_eq!(eval("2^-1").unwrap(), Value::Decimal(0.5));
+        assert!(matches!(eval("2^0.5").unwrap(), Value::Decimal(_)));
+    }
+
+    #[test]
+    fn overflow_follows_the_interpreter_mode() {
+        let run = |overflow, source| {
+            let mut interpreter = Interpreter::new();
+
+            interpreter.set_overflow(overflow);
+
+            eval_with(&mut interpreter, source)
+        };
+
+        for source in ["9223372036854775807 + 1", "3037000500 * 3037000500", "2^64", "[9223372036854775807] + 1"] {
+            assert_eq!(run(Overflow::Checked, source).unwrap_err().code(), ErrorCode::Overflow, "{}", source);
         }
+
+        assert_eq!(run(Overflow::Promote, "9223372036854775807 + 1").unwrap(), Value::Decimal(9223372036854775808.0));
+        assert_eq!(run(Overflow::Promote, "2^64").unwrap(), Value::Decimal(18446744073709551616.0));
+        assert_eq!(format!("{:#}", run(Overflow::Widen, "2^64").unwrap()), "18446744073709551616");
+        assert!(matches!(run(Overflow::Widen, "9223372036854775807 + 1").unwrap(), Value::BigInt(_)));
+        assert_eq!(format!("{:#}", run(Overflow::Widen, "[9223372036854775807] + 1").unwrap()), "[9223372036854775808]");
+        assert_eq!(run(Overflow::Widen, "1 + 1").unwrap(), Value::Number(2));
+    }
+
+    #[test]
+    fn negating_the_smallest_number_overflows() {
+        let err = eval("x = -9223372036854775807 - 1; -x").unwrap_err();
+
+        assert_eq!(err.code(), ErrorCode::Overflow);
+        assert!(err.to_string().contains("Integer overflow in -(-9223372036854775808)"), "{}", err);
+
+        let mut interpreter = Interpreter::new();
+
+        interpreter.set_overflow(Overflow::Widen);
+
+        let text = |interpreter: &mut Interpreter, source| format!("{:#}", eval_with(interpreter, source).unwrap());
+
+        assert_eq!(text(&mut interpreter, "-(-9223372036854775807 - 1)"), "9223372036854775808");
+        assert_eq!(text(&mut interpreter, "[[9223372036854775807, 1]] * 2"), "[[18446744073709552000, 2]]");
+    }
+
+    #[test]
+    fn complex_literals_and_natives() {
+        let text = |source| format!("{:#}", eval(source).unwrap());
+
+        assert_eq!(text("(1+2i) * (3-1i)"), "5+5i");
+        assert_eq!(text("abs(3+4i)"), "5");
+        assert_eq!(text("[re(2-5i), im(2-5i)]"), "[2, -5]");
+        assert_eq!(text("conj(2-5i)"), "2+5i");
+        assert_eq!(text("1i^2"), "-1+0i");
+        assert_eq!(text("[[1, 1i], [0, 2]] * [[1], [1]]"), "[[1+1i], [2+0i]]");
+    }
+
+    #[test]
+    fn negative_roots_and_logarithms_are_complex() {
+        let text = |source| format!("{:#}", eval(source).unwrap());
+
+        assert_eq!(text("sqrt(-4)"), "0+2i");
+        assert_eq!(text("ln(-1) == pi * 1i"), "true");
+        assert_eq!(text("log10(-100)"), text("2 + pi / ln(10) * 1i"));
+        assert_eq!(eval("ln(0)").unwrap_err().code(), ErrorCode::Domain);
+        assert_eq!(eval("log10([1, 0])").unwrap_err().code(), ErrorCode::Domain);
+    }
+
+    #[test]
+    fn min_and_max_reduce_only_when_asked() {
+        let text = |source| format!("{:#}", eval(source).unwrap());
+
+        assert_eq!(text("max([-1, 2, -3], 0)"), "[0, 2, 0]");
+        assert_eq!(text("max([-1, 2, -3])"), "2");
+        assert_eq!(text("max([[1, 5], [4, 2]], [], 0)"), "[4, 5]");
+        assert_eq!(text("min([[1, 5], [4, 2]], [], 1)"), "[1, 2]");
+        assert_eq!(text("min([[1, 5], [4, 2]], 2)"), "[[1, 2], [2, 2]]");
+        assert_eq!(eval("max([1, 2], [], 3)").unwrap_err().code(), ErrorCode::IndexOutOfBounds);
     }
-}
\ No newline at end of file
+}
@@ -1,5 +1,7 @@
 use std::fmt;
 use std::iter::{Iterator};
+use unicode_xid::UnicodeXID;
+use crate::span::Span;
 
 #[derive(PartialEq, Debug)]
 pub enum TokenKind {
@@ -8,6 +10,16 @@ pub enum TokenKind {
     Mul,
     Div,
     Exp,
+    ElemMul,
+    ElemDiv,
+    ElemExp,
+    Less,
+    Greater,
+    LessEqual,
+    GreaterEqual,
+    Equal,
+    NotEqual,
+    Range,
     Assign,
     LeftParen,
     RightParen,
@@ -21,7 +33,15 @@ pub enum TokenKind {
     Identifier,
     Number,
     Decimal,
-    Illegal,
+    Imaginary,
+    /// A double-quoted string; the value keeps the quotes and escapes.
+    String,
+    /// `√`, a prefix square root.
+    Sqrt,
+    /// A `///` comment; the value is its text without the slashes.
+    DocComment,
+    /// A malformed literal, with the reason it was rejected.
+    Illegal(&'static str),
     Unknown
 }
 
@@ -29,30 +49,48 @@ pub enum TokenKind {
 pub struct Token<'a> {
     kind: TokenKind,
     value: &'a str,
+    span: Span,
 }
 
 impl<'a> Token<'a> {
     pub fn kind(&self) -> &TokenKind {
-        return &self.kind
+        &self.kind
     }
 
     pub fn value(&self) -> &'a str {
-        return &self.value
+        self.value
     }
 
-    pub fn precedence(&self) -> usize {
-        match self.kind {
-            TokenKind::Add | TokenKind::Sub => 10,
-            TokenKind::Mul | TokenKind::Div => 20,
-            TokenKind::Exp => 30,
-            _ => 0,
-        }
+    pub fn span(&self) -> Span {
+        self.span
     }
 }
 
 pub struct Lexer<'a> {
     source: &'a str,
     position: usize,
+    line: usize,
+    column: usize,
+}
+
+fn is_digit(c: &str, radix: u32) -> bool {
+    c.len() == 1 && c.chars().all(|c| c.is_digit(radix))
+}
+
+/// Identifiers follow the Unicode XID rules, and may also start with `_`.
+fn is_identifier_start(c: &str) -> bool {
+    c.chars().all(|c| c == '_' || c.is_xid_start())
+}
+
+fn is_identifier_continue(c: &str) -> bool {
+    c != "\0" && c.chars().all(|c| c.is_xid_continue())
+}
+
+/// The column of byte offset `position`, counted in characters.
+fn column_of(source: &str, position: usize) -> usize {
+    let line_start = source[..position].rfind('\n').map_or(0, |i| i + 1);
+
+    source[line_start..position].chars().count() + 1
 }
 
 impl<'a> Lexer<'a> {
@@ -60,38 +98,212 @@ impl<'a> Lexer<'a> {
         Lexer {
             source,
             position: 0,
+            line: 1,
+            column: 1,
+        }
+    }
+
+    /// Starts lexing at a byte offset into `source`, so that spans stay relative
+    /// to the whole text. The REPL uses this to lex each line against its history.
+    pub fn from_position(source: &'a str, position: usize) -> Lexer<'a> {
+        let before = &source[..position];
+
+        Lexer {
+            source,
+            position,
+            line: before.matches('\n').count() + 1,
+            column: column_of(source, position),
+        }
+    }
+
+    /// The span of the (empty) end of input, used to report unexpected EOF.
+    /// Trailing whitespace is skipped so the location sits right after the last token.
+    pub fn end(&self) -> Span {
+        let text = self.source.trim_end();
+        let len = text.len();
+        let line = text.matches('\n').count() + 1;
+        let column = column_of(text, len);
+
+        Span::new(len, len, line, column)
+    }
+
+    /// The character starting at byte offset `pos`, or `"\0"` past the end.
+    fn char_at(&self, pos: usize) -> &'a str {
+        match self.source.get(pos..).and_then(|rest| rest.chars().next()) {
+            Some(c) => &self.source[pos..pos + c.len_utf8()],
+            None => "\0",
         }
     }
 
     fn peek(&mut self) -> &'a str {
-        let pos = self.position;
+        self.char_at(self.position)
+    }
 
-        self.source.get(pos..pos + 1).unwrap_or("\0")
+    fn peek_next(&mut self) -> &'a str {
+        let len = self.peek().len();
+
+        self.char_at(self.position + len)
     }
 
     fn get(&mut self) -> &'a str {
-        let pos = self.position;
+        let char = self.peek();
+
+        self.position += char.len();
+
+        if char == "\n" {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+
+        char
+    }
 
-        self.position += 1;
-        self.source.get(pos..pos + 1).unwrap_or("\0")
+    fn span_from(&self, start: usize, line: usize, column: usize) -> Span {
+        Span::new(start, self.position.min(self.source.len()), line, column)
     }
 
     fn is_space(&mut self) -> bool {
         let c = self.peek();
 
-        matches!(c, " " | "\t" | "\r" | "\n")
+        c.chars().all(char::is_whitespace) && c != "\0"
     }
 
     fn atom(&mut self, kind: TokenKind) -> Token<'a> {
+        let (start, line, column) = (self.position, self.line, self.column);
+        let value = self.get();
+
         Token {
             kind,
-            value: self.get(),
+            value,
+            span: self.span_from(start, line, column),
         }
     }
 
+    /// Reads a one or two character operator, e.g. `<` or `<=`.
+    fn atom_or_pair(&mut self, kind: TokenKind, second: &str, pair: TokenKind) -> Token<'a> {
+        if self.peek_next() != second {
+            return self.atom(kind);
+        }
+
+        let (start, line, column) = (self.position, self.line, self.column);
+
+        self.get();
+        self.get();
+
+        Token {
+            kind: pair,
+            value: &self.source[start..self.position],
+            span: self.span_from(start, line, column),
+        }
+    }
+
+    /// Reads a one character stand-in for an existing token, e.g. `∞` for `inf`. The
+    /// token takes `value` as its text and the alias's location as its span.
+    fn alias(&mut self, kind: TokenKind, value: &'static str) -> Token<'a> {
+        let (start, line, column) = (self.position, self.line, self.column);
+
+        self.get();
+
+        Token {
+            kind,
+            value,
+            span: self.span_from(start, line, column),
+        }
+    }
+
+    /// Moves to byte offset `end`, keeping the line and column in step.
+    fn advance_to(&mut self, end: usize) {
+        for c in self.source[self.position..end].chars() {
+            if c == '\n' {
+                self.line += 1;
+                self.column = 1;
+            } else {
+                self.column += 1;
+            }
+        }
+
+        self.position = end;
+    }
+
+    /// Skips a `#` or `//` comment up to the end of the line. A `///` comment is
+    /// returned as a doc comment token instead.
+    fn read_line_comment(&mut self) -> Option<Token<'a>> {
+        let (start, line, column) = (self.position, self.line, self.column);
+        let rest = &self.source[start..];
+        let end = rest.find('\n').map_or(self.source.len(), |i| start + i);
+
+        self.advance_to(end);
+
+        if !rest.starts_with("///") || rest.starts_with("////") {
+            return None;
+        }
+
+        let text = &self.source[start + 3..end];
+
+        Some(Token {
+            kind: TokenKind::DocComment,
+            value: text.strip_prefix(' ').unwrap_or(text).trim_end(),
+            span: self.span_from(start, line, column),
+        })
+    }
+
+    /// Skips a `/* */` comment, which may nest. An unterminated comment is returned
+    /// as an illegal token.
+    fn skip_block_comment(&mut self) -> Option<Token<'a>> {
+        let (start, line, column) = (self.position, self.line, self.column);
+        let bytes = self.source.as_bytes();
+        let mut depth = 0;
+        let mut pos = start;
+
+        while pos < bytes.len() {
+            match &bytes[pos..(pos + 2).min(bytes.len())] {
+                b"/*" => depth += 1,
+                b"*/" => depth -= 1,
+                _ => {
+                    pos += 1;
+                    continue;
+                },
+            }
+
+            pos += 2;
+
+            if depth == 0 {
+                self.advance_to(pos);
+
+                return None;
+            }
+        }
+
+        self.advance_to(start + 2);
+
+        let token = Token {
+            kind: TokenKind::Illegal("unterminated block comment"),
+            value: &self.source[start..start + 2],
+            span: self.span_from(start, line, column),
+        };
+
+        self.advance_to(bytes.len());
+
+        Some(token)
+    }
+
     fn read(&mut self) -> Option<Token<'a>> {
-        while self.is_space() {
-            self.get();
+        loop {
+            while self.is_space() {
+                self.get();
+            }
+
+            let token = match (self.peek(), self.peek_next()) {
+                ("#", _) | ("/", "/") => self.read_line_comment(),
+                ("/", "*") => self.skip_block_comment(),
+                _ => break,
+            };
+
+            if token.is_some() {
+                return token;
+            }
         }
 
         match self.peek() {
@@ -101,7 +313,17 @@ impl<'a> Lexer<'a> {
             "*" => Some(self.atom(TokenKind::Mul)),
             "/" => Some(self.atom(TokenKind::Div)),
             "^" => Some(self.atom(TokenKind::Exp)),
-            "=" => Some(self.atom(TokenKind::Assign)),
+            "<" => Some(self.atom_or_pair(TokenKind::Less, "=", TokenKind::LessEqual)),
+            ">" => Some(self.atom_or_pair(TokenKind::Greater, "=", TokenKind::GreaterEqual)),
+            "=" => Some(self.atom_or_pair(TokenKind::Assign, "=", TokenKind::Equal)),
+            "!" => Some(self.atom_or_pair(TokenKind::Unknown, "=", TokenKind::NotEqual)),
+            "." if is_digit(self.peek_next(), 10) => Some(self.read_number()),
+            "." => Some(match self.peek_next() {
+                "*" => self.atom_or_pair(TokenKind::Unknown, "*", TokenKind::ElemMul),
+                "/" => self.atom_or_pair(TokenKind::Unknown, "/", TokenKind::ElemDiv),
+                "^" => self.atom_or_pair(TokenKind::Unknown, "^", TokenKind::ElemExp),
+                _ => self.atom_or_pair(TokenKind::Unknown, ".", TokenKind::Range),
+            }),
             "(" => Some(self.atom(TokenKind::LeftParen)),
             ")" => Some(self.atom(TokenKind::RightParen)),
             "[" => Some(self.atom(TokenKind::ArrayStart)),
@@ -111,77 +333,182 @@ impl<'a> Lexer<'a> {
             "," => Some(self.atom(TokenKind::Separator)),
             ":" => Some(self.atom(TokenKind::Colon)),
             ";" => Some(self.atom(TokenKind::Semicolon)),
-            s => {
-                let b = s.as_bytes()[0];
-
-                if b.is_ascii_digit() {
-                    Some(self.read_number())
-                } else if b.is_ascii_alphabetic() {
-                    Some(self.read_identifier())
-                } else {
-                    Some(self.atom(TokenKind::Unknown))
-                }
-            }
+            "\"" => Some(self.read_string()),
+            "×" => Some(self.atom(TokenKind::Mul)),
+            "÷" => Some(self.atom(TokenKind::Div)),
+            "≤" => Some(self.atom(TokenKind::LessEqual)),
+            "≥" => Some(self.atom(TokenKind::GreaterEqual)),
+            "≠" => Some(self.atom(TokenKind::NotEqual)),
+            "√" => Some(self.atom(TokenKind::Sqrt)),
+            "∞" => Some(self.alias(TokenKind::Decimal, "inf")),
+            s if is_digit(s, 10) => Some(self.read_number()),
+            s if is_identifier_start(s) => Some(self.read_identifier()),
+            _ => Some(self.atom(TokenKind::Unknown)),
+        }
+    }
+
+    /// Consumes digits valid in `radix` along with `_` separators.
+    fn read_digits(&mut self, radix: u32) {
+        while self.peek() == "_" || is_digit(self.peek(), radix) {
+            self.get();
         }
     }
 
+    /// Reads a number: decimal digits with an optional fraction and exponent (`6.02e23`,
+    /// `.5`), or an integer with a `0x` or `0b` prefix. `_` may separate digits, and an
+    /// `i` suffix makes the number imaginary.
     fn read_number(&mut self) -> Token<'a> {
-        let start = self.position;
+        let (start, line, column) = (self.position, self.line, self.column);
         let mut kind = TokenKind::Number;
 
-        let mut char: &str;
-        let mut byte: u8;
+        let radix = match (self.peek(), self.peek_next()) {
+            ("0", "x" | "X") => 16,
+            ("0", "b" | "B") => 2,
+            _ => 10,
+        };
 
-        self.get();
+        if radix != 10 {
+            self.get();
+            self.get();
+            self.read_digits(radix);
 
-        loop {
-            char = self.peek();
-            byte = char.as_bytes()[0];
+            let digits = &self.source[start + 2..self.position];
 
-            if char == "." {
-                if kind == TokenKind::Decimal {
-                    kind = TokenKind::Illegal;
+            if is_identifier_continue(self.peek()) {
+                // swallow the rest of the word so it is reported as one token
+                while is_identifier_continue(self.peek()) {
+                    self.get();
+                }
 
-                    break;
-                } else {
-                    kind = TokenKind::Decimal;
+                kind = TokenKind::Illegal(if radix == 16 { "invalid hexadecimal digit" } else { "invalid binary digit" });
+            } else if digits.is_empty() {
+                kind = TokenKind::Illegal("missing digits after the radix prefix");
+            }
+        } else {
+            self.read_digits(10);
+
+            // `1..5` is a range and `2.*x` an element-wise product, not decimals
+            if self.peek() == "." && !matches!(self.peek_next(), "." | "*" | "/" | "^") {
+                self.get();
+                self.read_digits(10);
+                kind = TokenKind::Decimal;
+
+                if self.source[start..self.position].ends_with('.') {
+                    kind = TokenKind::Illegal("missing digits after the decimal point");
+                } else if self.peek() == "." && is_digit(self.peek_next(), 10) {
+                    self.get();
+                    self.read_digits(10);
+                    kind = TokenKind::Illegal("more than one decimal point");
                 }
-            } else if !byte.is_ascii_digit() {
-                break;
             }
 
-            self.get();
+            let exponent = matches!(self.peek(), "e" | "E")
+                && (is_digit(self.peek_next(), 10) || matches!(self.peek_next(), "+" | "-"));
+
+            if exponent && !matches!(kind, TokenKind::Illegal(_)) {
+                self.get();
+
+                if matches!(self.peek(), "+" | "-") {
+                    self.get();
+                }
+
+                let digits = self.position;
+
+                self.read_digits(10);
+                kind = TokenKind::Decimal;
+
+                if self.position == digits {
+                    kind = TokenKind::Illegal("missing digits in the exponent");
+                }
+            }
+        }
+
+        let text = &self.source[start..self.position];
+        // the neighbours must be digits of this radix, so `1_e5` and `0x_1` are rejected
+        let misplaced_separator = text.char_indices().any(|(i, c)| {
+            let digit = |c: Option<char>| c.is_some_and(|c| c.is_digit(radix));
+
+            c == '_' && !(digit(text[..i].chars().last()) && digit(text[i + 1..].chars().next()))
+        });
+
+        if misplaced_separator && !matches!(kind, TokenKind::Illegal(_)) {
+            kind = TokenKind::Illegal("'_' must separate two digits");
         }
 
         let end = self.position;
-        let last_char = &self.source[end - 1..end];
 
-        if last_char == "." {
-            kind = TokenKind::Illegal;
+        // an `i` suffix makes an imaginary literal, e.g. `4i`
+        if radix == 10 && matches!(kind, TokenKind::Number | TokenKind::Decimal)
+            && self.peek() == "i" && !is_identifier_continue(self.peek_next()) {
+            self.get();
+
+            return Token {
+                kind: TokenKind::Imaginary,
+                value: &self.source[start..end],
+                span: self.span_from(start, line, column),
+            };
         }
 
         Token {
             kind,
             value: &self.source[start..end],
+            span: self.span_from(start, line, column),
         }
     }
 
-    fn read_identifier(&mut self) -> Token<'a> {
-        let start = self.position;
-
-        let mut char: &str;
-        let mut byte: u8;
+    /// Reads a double-quoted string on a single line. The escapes `\n`, `\t`, `\r`, `\0`, `\\` and `\"`
+    /// are checked here and replaced by the parser.
+    fn read_string(&mut self) -> Token<'a> {
+        let (start, line, column) = (self.position, self.line, self.column);
+        let mut kind = TokenKind::String;
 
         self.get();
 
         loop {
-            char = self.peek();
-            byte = char.as_bytes()[0];
+            match self.peek() {
+                "\"" => {
+                    self.get();
 
-            if !byte.is_ascii_alphanumeric() {
-                break;
+                    break;
+                },
+                "\n" => {
+                    kind = TokenKind::Illegal("unterminated string");
+
+                    break;
+                },
+                "\0" if self.position >= self.source.len() => {
+                    kind = TokenKind::Illegal("unterminated string");
+
+                    break;
+                },
+                "\\" => {
+                    self.get();
+
+                    if matches!(self.peek(), "n" | "t" | "r" | "0" | "\\" | "\"") {
+                        self.get();
+                    } else {
+                        kind = TokenKind::Illegal("unknown escape sequence");
+                    }
+                },
+                _ => {
+                    self.get();
+                },
             }
+        }
+
+        Token {
+            kind,
+            value: &self.source[start..self.position],
+            span: self.span_from(start, line, column),
+        }
+    }
+
+    fn read_identifier(&mut self) -> Token<'a> {
+        let (start, line, column) = (self.position, self.line, self.column);
+
+        self.get();
 
+        while is_identifier_continue(self.peek()) {
             self.get();
         }
 
@@ -190,6 +517,7 @@ impl<'a> Lexer<'a> {
         Token {
             kind: TokenKind::Identifier,
             value: &self.source[start..end],
+            span: self.span_from(start, line, column),
         }
     }
 }
@@ -210,6 +538,16 @@ impl fmt::Display for TokenKind {
             TokenKind::Mul => write!(f, "Mul"),
             TokenKind::Div => write!(f, "Div"),
             TokenKind::Exp => write!(f, "Exp"),
+            TokenKind::ElemMul => write!(f, "ElemMul"),
+            TokenKind::ElemDiv => write!(f, "ElemDiv"),
+            TokenKind::ElemExp => write!(f, "ElemExp"),
+            TokenKind::Less => write!(f, "Less"),
+            TokenKind::Greater => write!(f, "Greater"),
+            TokenKind::LessEqual => write!(f, "LessEqual"),
+            TokenKind::GreaterEqual => write!(f, "GreaterEqual"),
+            TokenKind::Equal => write!(f, "Equal"),
+            TokenKind::NotEqual => write!(f, "NotEqual"),
+            TokenKind::Range => write!(f, "Range"),
             TokenKind::Assign => write!(f, "Assign"),
             TokenKind::LeftParen => write!(f, "LeftParen"),
             TokenKind::RightParen => write!(f, "RightParen"),
@@ -223,7 +561,11 @@ impl fmt::Display for TokenKind {
             TokenKind::Identifier => write!(f, "Identifier"),
             TokenKind::Number => write!(f, "Number"),
             TokenKind::Decimal => write!(f, "Decimal"),
-            TokenKind::Illegal => write!(f, "Illegal"),
+            TokenKind::Imaginary => write!(f, "Imaginary"),
+            TokenKind::String => write!(f, "String"),
+            TokenKind::Sqrt => write!(f, "Sqrt"),
+            TokenKind::DocComment => write!(f, "DocComment"),
+            TokenKind::Illegal(reason) => write!(f, "Illegal({})", reason),
             TokenKind::Unknown => write!(f, "Unknown"),
         }
     }
@@ -237,4 +579,84 @@ impl<'a> fmt::Display for Token<'a> {
             write!(f, "Token({}, {})", self.kind, self.value)
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn first(source: &str) -> (TokenKind, &str) {
+        let token = Lexer::new(source).next().unwrap();
+
+        (token.kind, token.value)
+    }
+
+    #[test]
+    fn number_literals() {
+        assert_eq!(first("42"), (TokenKind::Number, "42"));
+        assert_eq!(first("0xFF"), (TokenKind::Number, "0xFF"));
+        assert_eq!(first("0b1010"), (TokenKind::Number, "0b1010"));
+        assert_eq!(first("1.5"), (TokenKind::Decimal, "1.5"));
+        assert_eq!(first("1e-3"), (TokenKind::Decimal, "1e-3"));
+        assert_eq!(first("2.5E+10"), (TokenKind::Decimal, "2.5E+10"));
+    }
+
+    #[test]
+    fn dots_after_integers_are_operators() {
+        assert_eq!(first("1..5"), (TokenKind::Number, "1"));
+        assert_eq!(first("2.*x"), (TokenKind::Number, "2"));
+    }
+
+    #[test]
+    fn malformed_literals_are_illegal() {
+        for source in ["1.", "1.2.3", "1e+", "0x", "0xG1", "0b102"] {
+            assert!(matches!(first(source).0, TokenKind::Illegal(_)), "{} should be illegal", source);
+        }
+    }
+
+    #[test]
+    fn separators_must_sit_between_digits() {
+        assert_eq!(first("1_000_000"), (TokenKind::Number, "1_000_000"));
+        assert_eq!(first("0xFF_FF"), (TokenKind::Number, "0xFF_FF"));
+
+        for source in ["1__0", "1_", "0x_1", "1_e5", "1.5_e3", "1_.5", "0b1_2"] {
+            assert!(matches!(first(source).0, TokenKind::Illegal(_)), "{} should be illegal", source);
+        }
+    }
+
+    #[test]
+    fn tokens_carry_their_position() {
+        let spans: Vec<Span> = Lexer::new("a = 1\n  bb + π").map(|token| token.span).collect();
+
+        assert_eq!(spans, [
+            Span::new(0, 1, 1, 1),
+            Span::new(2, 3, 1, 3),
+            Span::new(4, 5, 1, 5),
+            Span::new(8, 10, 2, 3),
+            Span::new(11, 12, 2, 6),
+            Span::new(13, 15, 2, 8),
+        ]);
+    }
+
+    fn values(source: &str) -> Vec<&str> {
+        Lexer::new(source).map(|token| token.value).collect()
+    }
+
+    #[test]
+    fn comments_are_skipped() {
+        assert_eq!(values("1 # one\n+ 2 // two"), ["1", "+", "2"]);
+        assert_eq!(values("1 /* a /* nested */ b */ + 2"), ["1", "+", "2"]);
+        assert_eq!(values("//// not a doc comment\nx"), ["x"]);
+    }
+
+    #[test]
+    fn doc_comments_and_unterminated_block_comments_are_tokens() {
+        let doc = Lexer::new("///  Squares x  \nfn").next().unwrap();
+
+        assert_eq!((doc.kind, doc.value), (TokenKind::DocComment, " Squares x"));
+
+        let tokens: Vec<TokenKind> = Lexer::new("1 /* a /* b */").map(|token| token.kind).collect();
+
+        assert_eq!(tokens, [TokenKind::Number, TokenKind::Illegal("unterminated block comment")]);
+    }
+}
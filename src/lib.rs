@@ -1,7 +1,17 @@
+pub mod span;
 pub mod error;
+pub mod diagnostics;
 pub mod lexer;
 pub mod ast;
 pub mod parser;
 pub mod symbol;
+pub mod bigint;
+pub mod rational;
 pub mod memory;
+pub mod complex;
+pub mod matrix;
+pub mod linalg;
+pub mod math;
+pub mod stats;
+pub mod strings;
 pub mod interpreter;
\ No newline at end of file
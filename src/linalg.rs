@@ -0,0 +1,1346 @@
+use crate::complex::Complex;
+use crate::error::{Error, ErrorCode};
+use crate::matrix::{Dense, Matrix};
+use crate::memory::Value;
+
+fn singular() -> Error {
+    Error::runtime("Matrix is singular").with_code(ErrorCode::SingularMatrix)
+}
+
+fn require_square(rows: usize, cols: usize) -> Result<(), Error> {
+    if rows != cols {
+        return Err(Error::runtime(format!("Expected a square matrix, got {}x{}", rows, cols))
+            .with_code(ErrorCode::ShapeMismatch));
+    }
+
+    Ok(())
+}
+
+fn identity(n: usize) -> Dense<f64> {
+    Dense::from_fn(n, n, |i, j| if i == j { 1.0 } else { 0.0 })
+}
+
+/// Largest entry of `a` in absolute value.
+fn largest(a: &Dense<f64>) -> f64 {
+    a.data().iter().fold(0.0_f64, |max, x| max.max(x.abs()))
+}
+
+/// Pivots at or below this magnitude are treated as zero.
+pub fn tolerance(a: &Dense<f64>) -> f64 {
+    f64::EPSILON * a.rows().max(a.cols()) as f64 * largest(a)
+}
+
+/// Index of the row in `from..a.rows()` with the largest entry in column `col`.
+fn pivot_row(a: &Dense<f64>, from: usize, col: usize) -> usize {
+    (from..a.rows())
+        .max_by(|&i, &j| a.get(i, col).abs().total_cmp(&a.get(j, col).abs()))
+        .unwrap_or(from)
+}
+
+/// LU factorization `PA = LU` of a square matrix, computed with partial pivoting.
+/// `L` (unit diagonal) and `U` share one buffer.
+pub struct Lu {
+    lu: Dense<f64>,
+    perm: Vec<usize>,
+    sign: f64,
+    singular: bool,
+}
+
+impl Lu {
+    pub fn new(a: &Dense<f64>) -> Result<Lu, Error> {
+        Lu::with_tolerance(a, tolerance(a))
+    }
+
+    /// Factorizes `a`, treating pivots at or below `tol` as zero.
+    pub fn with_tolerance(a: &Dense<f64>, tol: f64) -> Result<Lu, Error> {
+        require_square(a.rows(), a.cols())?;
+
+        let n = a.rows();
+        let mut lu = a.clone();
+        let mut perm: Vec<usize> = (0..n).collect();
+        let mut sign = 1.0;
+        let mut singular = false;
+
+        for k in 0..n {
+            let p = pivot_row(&lu, k, k);
+
+            if lu.get(p, k).abs() <= tol {
+                // nothing left to eliminate in this column
+                singular = true;
+
+                for i in k + 1..n {
+                    lu.set(i, k, 0.0);
+                }
+
+                continue;
+            }
+
+            if p != k {
+                lu.swap_rows(p, k);
+                perm.swap(p, k);
+                sign = -sign;
+            }
+
+            let pivot = lu.get(k, k);
+
+            for i in k + 1..n {
+                let factor = lu.get(i, k) / pivot;
+
+                lu.set(i, k, factor);
+
+                for j in k + 1..n {
+                    lu.set(i, j, lu.get(i, j) - factor * lu.get(k, j));
+                }
+            }
+        }
+
+        Ok(Lu {
+            lu,
+            perm,
+            sign,
+            singular,
+        })
+    }
+
+    pub fn is_singular(&self) -> bool {
+        self.singular
+    }
+
+    pub fn lower(&self) -> Dense<f64> {
+        let n = self.lu.rows();
+
+        Dense::from_fn(n, n, |i, j| match i.cmp(&j) {
+            std::cmp::Ordering::Greater => self.lu.get(i, j),
+            std::cmp::Ordering::Equal => 1.0,
+            std::cmp::Ordering::Less => 0.0,
+        })
+    }
+
+    pub fn upper(&self) -> Dense<f64> {
+        let n = self.lu.rows();
+
+        Dense::from_fn(n, n, |i, j| if i <= j { self.lu.get(i, j) } else { 0.0 })
+    }
+
+    /// The permutation matrix `P`.
+    pub fn permutation(&self) -> Dense<i64> {
+        let n = self.lu.rows();
+
+        Dense::from_fn(n, n, |i, j| (self.perm[i] == j) as i64)
+    }
+
+    pub fn det(&self) -> f64 {
+        if self.singular {
+            return 0.0;
+        }
+
+        (0..self.lu.rows()).fold(self.sign, |det, i| det * self.lu.get(i, i))
+    }
+
+    /// Solves `AX = B` for every column of `b`.
+    pub fn solve(&self, b: &Dense<f64>) -> Result<Dense<f64>, Error> {
+        let n = self.lu.rows();
+
+        if b.rows() != n {
+            return Err(Error::runtime(format!(
+                "Cannot solve a {}x{} system with a right-hand side of {} rows", n, n, b.rows()
+            )).with_code(ErrorCode::ShapeMismatch));
+        }
+
+        if self.singular {
+            return Err(singular());
+        }
+
+        let mut x = Dense::from_fn(n, b.cols(), |i, j| b.get(self.perm[i], j));
+
+        for col in 0..b.cols() {
+            for i in 0..n {
+                let sum = (0..i).fold(x.get(i, col), |sum, k| sum - self.lu.get(i, k) * x.get(k, col));
+
+                x.set(i, col, sum);
+            }
+
+            for i in (0..n).rev() {
+                let sum = (i + 1..n).fold(x.get(i, col), |sum, k| sum - self.lu.get(i, k) * x.get(k, col));
+
+                x.set(i, col, sum / self.lu.get(i, i));
+            }
+        }
+
+        Ok(x)
+    }
+
+    pub fn inverse(&self) -> Result<Dense<f64>, Error> {
+        let n = self.lu.rows();
+
+        self.solve(&identity(n))
+    }
+}
+
+/// The real `2n x 2n` matrix `[X -Y; Y X]`, which acts on stacked real and imaginary
+/// parts the way `X + iY` acts on a complex vector.
+fn embed(a: &Dense<Complex>) -> Dense<f64> {
+    let n = a.rows();
+
+    Dense::from_fn(2 * n, 2 * n, |i, j| {
+        let z = a.get(i % n, j % n);
+
+        match (i < n, j < n) {
+            (true, false) => -z.im,
+            (false, true) => z.im,
+            _ => z.re,
+        }
+    })
+}
+
+/// Solves complex systems through the equivalent real system of twice the size.
+pub struct ComplexLu {
+    lu: Lu,
+    n: usize,
+}
+
+impl ComplexLu {
+    pub fn new(a: &Dense<Complex>) -> Result<ComplexLu, Error> {
+        require_square(a.rows(), a.cols())?;
+
+        Ok(ComplexLu {
+            lu: Lu::new(&embed(a))?,
+            n: a.rows(),
+        })
+    }
+
+    pub fn solve(&self, b: &Dense<Complex>) -> Result<Dense<Complex>, Error> {
+        let n = self.n;
+
+        if b.rows() != n {
+            return Err(Error::runtime(format!(
+                "Cannot solve a {}x{} system with a right-hand side of {} rows", n, n, b.rows()
+            )).with_code(ErrorCode::ShapeMismatch));
+        }
+
+        let stacked = Dense::from_fn(2 * n, b.cols(), |i, j| if i < n { b.get(i, j).re } else { b.get(i - n, j).im });
+        let x = self.lu.solve(&stacked)?;
+
+        Ok(Dense::from_fn(n, b.cols(), |i, j| Complex::new(x.get(i, j), x.get(i + n, j))))
+    }
+
+    pub fn inverse(&self) -> Result<Dense<Complex>, Error> {
+        self.solve(&identity(self.n).map(Complex::from))
+    }
+}
+
+/// Determinant of a complex matrix by Gaussian elimination with partial pivoting.
+fn det_complex(a: &Dense<Complex>) -> Complex {
+    let n = a.rows();
+    let mut m = a.clone();
+    let mut det = Complex::new(1.0, 0.0);
+
+    for k in 0..n {
+        let p = (k..n).max_by(|&i, &j| m.get(i, k).abs().total_cmp(&m.get(j, k).abs())).unwrap_or(k);
+
+        if m.get(p, k).abs() == 0.0 {
+            return Complex::default();
+        }
+
+        if p != k {
+            m.swap_rows(p, k);
+            det = -det;
+        }
+
+        let pivot = m.get(k, k);
+
+        det = det * pivot;
+
+        for i in k + 1..n {
+            let factor = m.get(i, k) / pivot;
+
+            for j in k + 1..n {
+                m.set(i, j, m.get(i, j) - factor * m.get(k, j));
+            }
+        }
+    }
+
+    det
+}
+
+/// Exact determinant of an integer matrix using fraction-free (Bareiss) elimination.
+/// Returns `None` if an intermediate value overflows.
+fn det_exact(a: &Dense<i64>) -> Option<i64> {
+    let n = a.rows();
+    let mut m = a.map(|x| x as i128);
+    let mut sign = 1;
+    let mut prev = 1;
+
+    for k in 0..n {
+        if m.get(k, k) == 0 {
+            match (k + 1..n).find(|&i| m.get(i, k) != 0) {
+                Some(i) => {
+                    m.swap_rows(i, k);
+                    sign = -sign;
+                },
+                None => return Some(0),
+            }
+        }
+
+        for i in k + 1..n {
+            for j in k + 1..n {
+                let value = m.get(i, j).checked_mul(m.get(k, k))?
+                    .checked_sub(m.get(i, k).checked_mul(m.get(k, j))?)?;
+
+                m.set(i, j, value / prev);
+            }
+        }
+
+        prev = m.get(k, k);
+    }
+
+    i64::try_from(sign * m.get(n - 1, n - 1)).ok()
+}
+
+/// Rank of a matrix, counted as the number of pivots found by Gaussian elimination.
+pub fn rank_of(a: &Dense<f64>) -> usize {
+    let tol = tolerance(a);
+    let mut m = a.clone();
+    let mut rank = 0;
+
+    for col in 0..m.cols() {
+        if rank == m.rows() {
+            break;
+        }
+
+        let p = pivot_row(&m, rank, col);
+
+        if m.get(p, col).abs() <= tol {
+            continue;
+        }
+
+        m.swap_rows(p, rank);
+
+        for i in rank + 1..m.rows() {
+            let factor = m.get(i, col) / m.get(rank, col);
+
+            for j in col..m.cols() {
+                m.set(i, j, m.get(i, j) - factor * m.get(rank, j));
+            }
+        }
+
+        rank += 1;
+    }
+
+    rank
+}
+
+/// Stopping criteria for the iterative decompositions (`eig`, `svd`).
+#[derive(Debug, Clone, Copy)]
+pub struct Convergence {
+    /// Relative size below which an off-diagonal entry counts as zero.
+    pub tolerance: f64,
+    pub max_iterations: usize,
+}
+
+impl Default for Convergence {
+    fn default() -> Self {
+        Convergence {
+            tolerance: 1e-12,
+            max_iterations: 1000,
+        }
+    }
+}
+
+fn no_convergence(name: &str) -> Error {
+    Error::runtime(format!("{} did not converge", name)).with_code(ErrorCode::NoConvergence)
+}
+
+/// `|a|` with the sign of `b`.
+fn with_sign(a: f64, b: f64) -> f64 {
+    if b >= 0.0 { a.abs() } else { -a.abs() }
+}
+
+fn is_symmetric(a: &Dense<f64>, tolerance: f64) -> bool {
+    let scale = a.data().iter().fold(1.0_f64, |max, x| max.max(x.abs()));
+
+    (0..a.rows()).all(|i| (0..i).all(|j| (a.get(i, j) - a.get(j, i)).abs() <= tolerance * scale))
+}
+
+/// Householder QR factorization `A = QR` with an orthogonal `m x m` `Q` and an upper
+/// triangular `m x n` `R`. Columns whose remaining norm is at or below `tol` are left as they are.
+pub fn qr_of(a: &Dense<f64>, tol: f64) -> (Dense<f64>, Dense<f64>) {
+    let (m, n) = (a.rows(), a.cols());
+    let mut q = identity(m);
+    let mut r = a.clone();
+
+    for k in 0..n.min(m.saturating_sub(1)) {
+        let norm = (k..m).map(|i| r.get(i, k).powi(2)).sum::<f64>().sqrt();
+
+        if norm <= tol {
+            continue;
+        }
+
+        let mut v: Vec<f64> = (k..m).map(|i| r.get(i, k)).collect();
+
+        v[0] -= with_sign(norm, -r.get(k, k));
+
+        let scale = 2.0 / v.iter().map(|x| x * x).sum::<f64>();
+
+        // R = HR and Q = QH with H = I - 2vv'/v'v acting on rows (columns of Q) k..m
+        for j in 0..n {
+            let f = scale * v.iter().enumerate().map(|(i, x)| x * r.get(k + i, j)).sum::<f64>();
+
+            for (i, x) in v.iter().enumerate() {
+                r.set(k + i, j, r.get(k + i, j) - f * x);
+            }
+        }
+
+        for i in 0..m {
+            let f = scale * v.iter().enumerate().map(|(j, x)| x * q.get(i, k + j)).sum::<f64>();
+
+            for (j, x) in v.iter().enumerate() {
+                q.set(i, k + j, q.get(i, k + j) - f * x);
+            }
+        }
+
+        for i in k + 1..m {
+            r.set(i, k, 0.0);
+        }
+    }
+
+    (q, r)
+}
+
+/// Cholesky factorization `A = LL'` of a symmetric positive definite matrix. `tolerance` is
+/// relative to the largest entry and bounds both the asymmetry allowed and the smallest pivot.
+pub fn chol_of(a: &Dense<f64>, tolerance: f64) -> Result<Dense<f64>, Error> {
+    require_square(a.rows(), a.cols())?;
+
+    if !is_symmetric(a, tolerance) {
+        return Err(Error::runtime("chol expects a symmetric matrix").with_code(ErrorCode::InvalidOperation));
+    }
+
+    let n = a.rows();
+    let min_pivot = tolerance * largest(a);
+    let mut l = Dense::from_fn(n, n, |_, _| 0.0_f64);
+
+    for j in 0..n {
+        let d = a.get(j, j) - (0..j).map(|k| l.get(j, k).powi(2)).sum::<f64>();
+
+        if d <= min_pivot {
+            return Err(Error::runtime("Matrix is not positive definite").with_code(ErrorCode::InvalidOperation));
+        }
+
+        l.set(j, j, d.sqrt());
+
+        for i in j + 1..n {
+            let sum = (0..j).fold(a.get(i, j), |sum, k| sum - l.get(i, k) * l.get(j, k));
+
+            l.set(i, j, sum / l.get(j, j));
+        }
+    }
+
+    Ok(l)
+}
+
+/// Applies the rotation `(c, s)` to columns `p` and `q`.
+fn rotate_columns(a: &mut Dense<f64>, p: usize, q: usize, c: f64, s: f64) {
+    for k in 0..a.rows() {
+        let (x, y) = (a.get(k, p), a.get(k, q));
+
+        a.set(k, p, c * x - s * y);
+        a.set(k, q, s * x + c * y);
+    }
+}
+
+/// Tangent of the Jacobi rotation angle that annihilates an off-diagonal entry,
+/// given `zeta = (a_qq - a_pp) / 2a_pq`.
+fn jacobi_tangent(zeta: f64) -> f64 {
+    with_sign(1.0, zeta) / (zeta.abs() + (zeta * zeta + 1.0).sqrt())
+}
+
+/// Sorts eigen or singular values along with their vectors, which are stored as columns.
+fn sort_columns(values: Vec<f64>, vectors: &Dense<f64>, descending: bool) -> (Vec<f64>, Dense<f64>) {
+    let mut order: Vec<usize> = (0..values.len()).collect();
+
+    order.sort_by(|&i, &j| values[i].total_cmp(&values[j]));
+
+    if descending {
+        order.reverse();
+    }
+
+    let sorted = Dense::from_fn(vectors.rows(), order.len(), |i, j| vectors.get(i, order[j]));
+
+    (order.iter().map(|&i| values[i]).collect(), sorted)
+}
+
+/// Eigenvalues (ascending) and orthonormal eigenvectors of a symmetric matrix, using
+/// cyclic Jacobi rotations.
+pub fn eig_symmetric(a: &Dense<f64>, conv: &Convergence) -> Result<(Vec<f64>, Dense<f64>), Error> {
+    let n = a.rows();
+    let mut a = a.clone();
+    let mut v = identity(n);
+    let scale = a.data().iter().map(|x| x * x).sum::<f64>().sqrt();
+
+    for _ in 0..conv.max_iterations {
+        let off = (0..n)
+            .flat_map(|i| (0..n).filter(move |&j| j != i).map(move |j| (i, j)))
+            .map(|(i, j)| a.get(i, j).powi(2))
+            .sum::<f64>()
+            .sqrt();
+
+        if off <= conv.tolerance * scale {
+            return Ok(sort_columns((0..n).map(|i| a.get(i, i)).collect(), &v, false));
+        }
+
+        for p in 0..n {
+            for q in p + 1..n {
+                if a.get(p, q) == 0.0 {
+                    continue;
+                }
+
+                let t = jacobi_tangent((a.get(q, q) - a.get(p, p)) / (2.0 * a.get(p, q)));
+                let c = 1.0 / (t * t + 1.0).sqrt();
+                let s = t * c;
+
+                rotate_columns(&mut a, p, q, c, s);
+
+                for k in 0..n {
+                    let (x, y) = (a.get(p, k), a.get(q, k));
+
+                    a.set(p, k, c * x - s * y);
+                    a.set(q, k, s * x + c * y);
+                }
+
+                rotate_columns(&mut v, p, q, c, s);
+            }
+        }
+    }
+
+    Err(no_convergence("eig"))
+}
+
+/// Reduces a square matrix to upper Hessenberg form with Householder similarity
+/// transforms, which keeps its eigenvalues.
+fn hessenberg(a: &Dense<f64>) -> Dense<f64> {
+    let n = a.rows();
+    let mut h = a.clone();
+
+    for k in 0..n.saturating_sub(2) {
+        let norm = (k + 1..n).map(|i| h.get(i, k).powi(2)).sum::<f64>().sqrt();
+
+        if norm == 0.0 {
+            continue;
+        }
+
+        let mut v: Vec<f64> = (k + 1..n).map(|i| h.get(i, k)).collect();
+
+        v[0] -= with_sign(norm, -h.get(k + 1, k));
+
+        let scale = 2.0 / v.iter().map(|x| x * x).sum::<f64>();
+
+        for j in 0..n {
+            let f = scale * v.iter().enumerate().map(|(i, x)| x * h.get(k + 1 + i, j)).sum::<f64>();
+
+            for (i, x) in v.iter().enumerate() {
+                h.set(k + 1 + i, j, h.get(k + 1 + i, j) - f * x);
+            }
+        }
+
+        for i in 0..n {
+            let f = scale * v.iter().enumerate().map(|(j, x)| x * h.get(i, k + 1 + j)).sum::<f64>();
+
+            for (j, x) in v.iter().enumerate() {
+                h.set(i, k + 1 + j, h.get(i, k + 1 + j) - f * x);
+            }
+        }
+    }
+
+    h
+}
+
+/// Eigenvalues `(re, im)` of an upper Hessenberg matrix, found with the Francis
+/// double-shift QR algorithm. Complex eigenvalues come in conjugate pairs.
+fn hessenberg_eigenvalues(mut h: Dense<f64>, conv: &Convergence) -> Result<Vec<(f64, f64)>, Error> {
+    let n = h.rows();
+    let mut values = vec![(0.0, 0.0); n];
+    let norm = (0..n)
+        .flat_map(|i| (i.saturating_sub(1)..n).map(move |j| (i, j)))
+        .map(|(i, j)| h.get(i, j).abs())
+        .sum::<f64>();
+    let mut shift = 0.0;
+    let mut end = n;
+
+    while end > 0 {
+        let nn = end - 1;
+        let mut iterations = 0;
+
+        loop {
+            // find the start of the unreduced block ending at `nn`
+            let mut l = nn;
+
+            while l > 0 {
+                let s = h.get(l - 1, l - 1).abs() + h.get(l, l).abs();
+                let s = if s == 0.0 { norm } else { s };
+
+                if h.get(l, l - 1).abs() <= conv.tolerance * s {
+                    h.set(l, l - 1, 0.0);
+                    break;
+                }
+
+                l -= 1;
+            }
+
+            let mut x = h.get(nn, nn);
+
+            if l == nn {
+                values[nn] = (x + shift, 0.0);
+                end -= 1;
+                break;
+            }
+
+            let mut y = h.get(nn - 1, nn - 1);
+            let mut w = h.get(nn, nn - 1) * h.get(nn - 1, nn);
+
+            if l == nn - 1 {
+                // a 2x2 block: two real roots or a conjugate pair
+                let p = 0.5 * (y - x);
+                let q = p * p + w;
+                let z = q.abs().sqrt();
+
+                x += shift;
+
+                if q >= 0.0 {
+                    let z = p + with_sign(z, p);
+                    let other = if z != 0.0 { x - w / z } else { x + z };
+
+                    values[nn - 1] = (x + z, 0.0);
+                    values[nn] = (other, 0.0);
+                } else {
+                    values[nn - 1] = (x + p, -z);
+                    values[nn] = (x + p, z);
+                }
+
+                end -= 2;
+                break;
+            }
+
+            if iterations == conv.max_iterations {
+                return Err(no_convergence("eig"));
+            }
+
+            if iterations == 10 || iterations == 20 {
+                // exceptional shift to break cycles
+                shift += x;
+
+                for i in 0..=nn {
+                    h.set(i, i, h.get(i, i) - x);
+                }
+
+                let s = h.get(nn, nn - 1).abs() + h.get(nn - 1, nn - 2).abs();
+
+                x = 0.75 * s;
+                y = x;
+                w = -0.4375 * s * s;
+            }
+
+            iterations += 1;
+
+            // look for two consecutive small subdiagonal entries
+            let mut m = nn - 2;
+            let (mut p, mut q, mut r);
+
+            loop {
+                let z = h.get(m, m);
+                let (rr, ss) = (x - z, y - z);
+
+                p = (rr * ss - w) / h.get(m + 1, m) + h.get(m, m + 1);
+                q = h.get(m + 1, m + 1) - z - rr - ss;
+                r = h.get(m + 2, m + 1);
+
+                let s = p.abs() + q.abs() + r.abs();
+
+                p /= s;
+                q /= s;
+                r /= s;
+
+                if m == l {
+                    break;
+                }
+
+                let u = h.get(m, m - 1).abs() * (q.abs() + r.abs());
+                let v = p.abs() * (h.get(m - 1, m - 1).abs() + z.abs() + h.get(m + 1, m + 1).abs());
+
+                if u <= conv.tolerance * v {
+                    break;
+                }
+
+                m -= 1;
+            }
+
+            for i in m + 2..=nn {
+                h.set(i, i - 2, 0.0);
+
+                if i != m + 2 {
+                    h.set(i, i - 3, 0.0);
+                }
+            }
+
+            // double QR step on rows l..=nn and columns m..=nn
+            for k in m..nn {
+                if k != m {
+                    p = h.get(k, k - 1);
+                    q = h.get(k + 1, k - 1);
+                    r = if k != nn - 1 { h.get(k + 2, k - 1) } else { 0.0 };
+                    x = p.abs() + q.abs() + r.abs();
+
+                    if x != 0.0 {
+                        p /= x;
+                        q /= x;
+                        r /= x;
+                    }
+                }
+
+                let s = with_sign((p * p + q * q + r * r).sqrt(), p);
+
+                if s == 0.0 {
+                    continue;
+                }
+
+                if k == m {
+                    if l != m {
+                        h.set(k, k - 1, -h.get(k, k - 1));
+                    }
+                } else {
+                    h.set(k, k - 1, -s * x);
+                }
+
+                p += s;
+                x = p / s;
+                y = q / s;
+
+                let z = r / s;
+
+                q /= p;
+                r /= p;
+
+                for j in k..=nn {
+                    let mut p = h.get(k, j) + q * h.get(k + 1, j);
+
+                    if k != nn - 1 {
+                        p += r * h.get(k + 2, j);
+                        h.set(k + 2, j, h.get(k + 2, j) - p * z);
+                    }
+
+                    h.set(k + 1, j, h.get(k + 1, j) - p * y);
+                    h.set(k, j, h.get(k, j) - p * x);
+                }
+
+                for i in l..=nn.min(k + 3) {
+                    let mut p = x * h.get(i, k) + y * h.get(i, k + 1);
+
+                    if k != nn - 1 {
+                        p += z * h.get(i, k + 2);
+                        h.set(i, k + 2, h.get(i, k + 2) - p * r);
+                    }
+
+                    h.set(i, k + 1, h.get(i, k + 1) - p * q);
+                    h.set(i, k, h.get(i, k) - p);
+                }
+            }
+        }
+    }
+
+    Ok(values)
+}
+
+/// Eigenvector for a real eigenvalue, by inverse iteration with a slightly perturbed shift.
+fn eigenvector(a: &Dense<f64>, value: f64, conv: &Convergence) -> Result<Vec<f64>, Error> {
+    let n = a.rows();
+    let scale = a.data().iter().fold(1.0_f64, |max, x| max.max(x.abs()));
+    let shift = value + scale * 1e-10;
+    let lu = Lu::new(&Dense::from_fn(n, n, |i, j| a.get(i, j) - if i == j { shift } else { 0.0 }))?;
+    let normalize = |x: Vec<f64>| {
+        let norm = x.iter().map(|x| x * x).sum::<f64>().sqrt();
+        let largest = x.iter().fold(0.0_f64, |m, x| if x.abs() > m.abs() { *x } else { m });
+
+        x.iter().map(|x| x / with_sign(norm, largest)).collect::<Vec<f64>>()
+    };
+    let mut x = normalize(vec![1.0; n]);
+
+    for _ in 0..conv.max_iterations {
+        let y = normalize(lu.solve(&Dense::new(n, 1, x.clone())?)?.data().to_vec());
+        let change = x.iter().zip(y.iter()).map(|(a, b)| (a - b).abs()).fold(0.0, f64::max);
+
+        x = y;
+
+        if change <= conv.tolerance.sqrt() {
+            return Ok(x);
+        }
+    }
+
+    Err(no_convergence("eig"))
+}
+
+/// Eigenvector for a complex eigenvalue, by complex inverse iteration. The vector is
+/// scaled to unit length with its largest entry real and positive.
+fn complex_eigenvector(a: &Dense<f64>, value: Complex, conv: &Convergence) -> Result<Vec<Complex>, Error> {
+    let n = a.rows();
+    let scale = a.data().iter().fold(1.0_f64, |max, x| max.max(x.abs()));
+    let shift = value + Complex::from(scale * 1e-10);
+    let lu = ComplexLu::new(&Dense::from_fn(n, n, |i, j| {
+        Complex::from(a.get(i, j)) - if i == j { shift } else { Complex::default() }
+    }))?;
+    let normalize = |x: Vec<Complex>| {
+        let norm = x.iter().map(|x| x.abs().powi(2)).sum::<f64>().sqrt();
+        let largest = x.iter().fold(Complex::default(), |m, x| if x.abs() > m.abs() { *x } else { m });
+        let phase = largest / Complex::from(largest.abs());
+
+        x.iter().map(|x| *x / (phase * Complex::from(norm))).collect::<Vec<Complex>>()
+    };
+    let mut x = normalize(vec![Complex::from(1.0); n]);
+
+    for _ in 0..conv.max_iterations {
+        let y = normalize(lu.solve(&Dense::new(n, 1, x.clone())?)?.data().to_vec());
+        let change = x.iter().zip(y.iter()).map(|(a, b)| (*a - *b).abs()).fold(0.0, f64::max);
+
+        x = y;
+
+        if change <= conv.tolerance.sqrt() {
+            return Ok(x);
+        }
+    }
+
+    Err(no_convergence("eig"))
+}
+
+/// Eigenvalues and eigenvectors (one per column) of a real matrix.
+pub enum Eigen {
+    Real(Vec<f64>, Dense<f64>),
+    /// At least one eigenvalue is complex; they are sorted by real then imaginary part.
+    Complex(Vec<Complex>, Dense<Complex>),
+}
+
+/// Eigenvalues (ascending) and eigenvectors of a general real matrix. Symmetric input
+/// takes the Jacobi path; otherwise the eigenvalues come from the Hessenberg QR
+/// algorithm and each vector from inverse iteration.
+pub fn eig_of(a: &Dense<f64>, conv: &Convergence) -> Result<Eigen, Error> {
+    require_square(a.rows(), a.cols())?;
+
+    if is_symmetric(a, conv.tolerance) {
+        let (values, vectors) = eig_symmetric(a, conv)?;
+
+        return Ok(Eigen::Real(values, vectors));
+    }
+
+    let n = a.rows();
+    let mut pairs = hessenberg_eigenvalues(hessenberg(a), conv)?;
+
+    if pairs.iter().all(|(_, im)| *im == 0.0) {
+        let values: Vec<f64> = pairs.into_iter().map(|(re, _)| re).collect();
+        let mut vectors = Dense::from_fn(n, n, |_, _| 0.0);
+
+        for (j, value) in values.iter().enumerate() {
+            for (i, x) in eigenvector(a, *value, conv)?.into_iter().enumerate() {
+                vectors.set(i, j, x);
+            }
+        }
+
+        let (values, vectors) = sort_columns(values, &vectors, false);
+
+        return Ok(Eigen::Real(values, vectors));
+    }
+
+    pairs.sort_by(|a, b| a.0.total_cmp(&b.0).then(a.1.total_cmp(&b.1)));
+
+    let values: Vec<Complex> = pairs.into_iter().map(|(re, im)| Complex::new(re, im)).collect();
+    let mut vectors = Dense::from_fn(n, n, |_, _| Complex::default());
+
+    for (j, value) in values.iter().enumerate() {
+        let vector = match value.im {
+            0.0 => eigenvector(a, value.re, conv)?.into_iter().map(Complex::from).collect(),
+            _ => complex_eigenvector(a, *value, conv)?,
+        };
+
+        for (i, x) in vector.into_iter().enumerate() {
+            vectors.set(i, j, x);
+        }
+    }
+
+    Ok(Eigen::Complex(values, vectors))
+}
+
+/// Thin singular value decomposition `A = U diag(S) V'`.
+pub struct Svd {
+    pub u: Dense<f64>,
+    pub s: Vec<f64>,
+    pub v: Dense<f64>,
+}
+
+/// Computes the SVD by one-sided Jacobi rotations. Singular values are in descending
+/// order; columns of `U` for zero singular values are left as zeros.
+pub fn svd_of(a: &Dense<f64>, conv: &Convergence) -> Result<Svd, Error> {
+    if a.rows() < a.cols() {
+        let Svd { u, s, v } = svd_of(&a.transpose(), conv)?;
+
+        return Ok(Svd { u: v, s, v: u });
+    }
+
+    let (m, n) = (a.rows(), a.cols());
+    let mut u = a.clone();
+    let mut v = identity(n);
+    let column_dot = |u: &Dense<f64>, p: usize, q: usize| (0..m).map(|i| u.get(i, p) * u.get(i, q)).sum::<f64>();
+
+    for _ in 0..conv.max_iterations {
+        let mut rotated = false;
+
+        for p in 0..n {
+            for q in p + 1..n {
+                let alpha = column_dot(&u, p, p);
+                let beta = column_dot(&u, q, q);
+                let gamma = column_dot(&u, p, q);
+
+                if gamma.abs() <= conv.tolerance * (alpha * beta).sqrt() {
+                    continue;
+                }
+
+                rotated = true;
+
+                let t = jacobi_tangent((beta - alpha) / (2.0 * gamma));
+                let c = 1.0 / (t * t + 1.0).sqrt();
+                let s = t * c;
+
+                rotate_columns(&mut u, p, q, c, s);
+                rotate_columns(&mut v, p, q, c, s);
+            }
+        }
+
+        if !rotated {
+            let values: Vec<f64> = (0..n).map(|j| column_dot(&u, j, j).sqrt()).collect();
+
+            for (j, sigma) in values.iter().enumerate() {
+                for i in 0..m {
+                    u.set(i, j, if *sigma > 0.0 { u.get(i, j) / sigma } else { 0.0 });
+                }
+            }
+
+            let (_, v) = sort_columns(values.clone(), &v, true);
+            let (s, u) = sort_columns(values, &u, true);
+
+            return Ok(Svd { u, s, v });
+        }
+    }
+
+    Err(no_convergence("svd"))
+}
+
+pub(crate) fn expect_arity(name: &str, args: &[Value], count: usize) -> Result<(), Error> {
+    if args.len() != count {
+        return Err(Error::runtime(format!("{} expects {} argument(s), got {}", name, count, args.len()))
+            .with_code(ErrorCode::ArityMismatch));
+    }
+
+    Ok(())
+}
+
+pub(crate) fn matrix_arg<'a>(name: &str, value: &'a Value) -> Result<&'a Matrix, Error> {
+    match value {
+        Value::Matrix(mat) => Ok(mat),
+        other => Err(Error::runtime(format!("{} expects a Matrix, got {}", name, other.kind()))
+            .with_code(ErrorCode::TypeMismatch)),
+    }
+}
+
+/// The entries of a real matrix argument; complex matrices are rejected.
+fn real_arg(name: &str, mat: &Matrix) -> Result<Dense<f64>, Error> {
+    if mat.is_complex() {
+        return Err(Error::runtime(format!("{} does not support complex matrices", name))
+            .with_code(ErrorCode::TypeMismatch));
+    }
+
+    Ok(mat.to_decimal())
+}
+
+/// The most entries `eye`, `zeros` and `ones` will allocate (128 MB of decimals).
+const MAX_ELEMENTS: usize = 1 << 24;
+
+fn size_arg(name: &str, value: &Value) -> Result<usize, Error> {
+    match value {
+        Value::Number(n) if *n > 0 => Ok(usize::try_from(*n).unwrap_or(usize::MAX)),
+        _ => Err(Error::runtime(format!("{} expects positive Number dimensions", name))
+            .with_code(ErrorCode::TypeMismatch)),
+    }
+}
+
+/// Reads `rows` and `cols`, refusing matrices with more than `MAX_ELEMENTS` entries.
+fn shape_arg(name: &str, rows: &Value, cols: &Value) -> Result<(usize, usize), Error> {
+    let (rows, cols) = (size_arg(name, rows)?, size_arg(name, cols)?);
+
+    match rows.checked_mul(cols) {
+        Some(size) if size <= MAX_ELEMENTS => Ok((rows, cols)),
+        _ => Err(Error::runtime(format!(
+            "{}: a {}x{} matrix is too large; the most is {} entries", name, rows, cols, MAX_ELEMENTS
+        )).with_code(ErrorCode::InvalidOperation)),
+    }
+}
+
+fn square_arg<'a>(name: &str, args: &'a [Value]) -> Result<&'a Matrix, Error> {
+    expect_arity(name, args, 1)?;
+
+    let mat = matrix_arg(name, &args[0])?;
+
+    require_square(mat.rows(), mat.cols())?;
+
+    Ok(mat)
+}
+
+pub(crate) fn decimal(mat: Dense<f64>) -> Value {
+    Value::Matrix(Matrix::Decimal(mat))
+}
+
+pub fn det(args: Vec<Value>) -> Result<Value, Error> {
+    let mat = square_arg("det", &args)?;
+
+    if let Some(det) = match mat {
+        Matrix::Number(m) => det_exact(m),
+        Matrix::Decimal(_) => None,
+        Matrix::Complex(m) => return Ok(Value::Complex(det_complex(m))),
+    } {
+        return Ok(Value::Number(det));
+    }
+
+    Ok(Value::Decimal(Lu::new(&mat.to_decimal())?.det()))
+}
+
+pub fn inv(args: Vec<Value>) -> Result<Value, Error> {
+    let mat = square_arg("inv", &args)?;
+
+    if let Matrix::Complex(m) = mat {
+        return Ok(Value::Matrix(Matrix::Complex(ComplexLu::new(m)?.inverse()?)));
+    }
+
+    Ok(decimal(Lu::new(&mat.to_decimal())?.inverse()?))
+}
+
+/// `solve(A, b)` solves `Ax = b` where `b` is a vector or a matrix of right-hand sides.
+pub fn solve(args: Vec<Value>) -> Result<Value, Error> {
+    expect_arity("solve", &args, 2)?;
+
+    if matrix_arg("solve", &args[0])?.is_complex() || is_complex_rhs(&args[1]) {
+        return solve_complex(&args);
+    }
+
+    let lu = Lu::new(&matrix_arg("solve", &args[0])?.to_decimal())?;
+
+    match &args[1] {
+        Value::Array(items) => {
+            let x = lu.solve(&Matrix::from_vector(items, true)?.to_decimal())?;
+
+            Ok(Value::Array(x.data().iter().map(|x| Value::Decimal(*x)).collect()))
+        },
+        Value::Matrix(b) => Ok(decimal(lu.solve(&b.to_decimal())?)),
+        other => Err(Error::runtime(format!("solve expects an Array or Matrix right-hand side, got {}", other.kind()))
+            .with_code(ErrorCode::TypeMismatch)),
+    }
+}
+
+fn is_complex_rhs(value: &Value) -> bool {
+    match value {
+        Value::Array(items) => items.iter().any(Value::is_complex),
+        Value::Matrix(mat) => mat.is_complex(),
+        _ => false,
+    }
+}
+
+fn solve_complex(args: &[Value]) -> Result<Value, Error> {
+    let lu = ComplexLu::new(&matrix_arg("solve", &args[0])?.to_complex())?;
+
+    match &args[1] {
+        Value::Array(items) => {
+            let x = lu.solve(&Matrix::from_vector(items, true)?.to_complex())?;
+
+            Ok(Value::Array(x.data().iter().map(|x| Value::Complex(*x)).collect()))
+        },
+        Value::Matrix(b) => Ok(Value::Matrix(Matrix::Complex(lu.solve(&b.to_complex())?))),
+        other => Err(Error::runtime(format!("solve expects an Array or Matrix right-hand side, got {}", other.kind()))
+            .with_code(ErrorCode::TypeMismatch)),
+    }
+}
+
+pub fn rank(args: Vec<Value>) -> Result<Value, Error> {
+    expect_arity("rank", &args, 1)?;
+
+    Ok(Value::Number(rank_of(&real_arg("rank", matrix_arg("rank", &args[0])?)?) as i64))
+}
+
+pub fn trace(args: Vec<Value>) -> Result<Value, Error> {
+    let mat = square_arg("trace", &args)?;
+
+    match mat {
+        Matrix::Number(m) => (0..m.rows())
+            .try_fold(0_i64, |sum, i| sum.checked_add(m.get(i, i)))
+            .map(Value::Number)
+            .ok_or_else(|| Error::runtime("Integer overflow in trace").with_code(ErrorCode::Overflow)),
+        Matrix::Decimal(m) => Ok(Value::Decimal((0..m.rows()).map(|i| m.get(i, i)).sum())),
+        Matrix::Complex(m) => Ok(Value::Complex((0..m.rows()).fold(Complex::default(), |sum, i| sum + m.get(i, i)))),
+    }
+}
+
+pub fn eye(args: Vec<Value>) -> Result<Value, Error> {
+    expect_arity("eye", &args, 1)?;
+
+    let (n, _) = shape_arg("eye", &args[0], &args[0])?;
+
+    Ok(Value::Matrix(Matrix::Number(Dense::from_fn(n, n, |i, j| (i == j) as i64))))
+}
+
+fn filled(name: &str, args: &[Value], value: i64) -> Result<Value, Error> {
+    expect_arity(name, args, 2)?;
+
+    let (rows, cols) = shape_arg(name, &args[0], &args[1])?;
+
+    Ok(Value::Matrix(Matrix::Number(Dense::from_fn(rows, cols, |_, _| value))))
+}
+
+pub fn zeros(args: Vec<Value>) -> Result<Value, Error> {
+    filled("zeros", &args, 0)
+}
+
+pub fn ones(args: Vec<Value>) -> Result<Value, Error> {
+    filled("ones", &args, 1)
+}
+
+fn decimals(values: Vec<f64>) -> Value {
+    Value::Array(values.into_iter().map(Value::Decimal).collect())
+}
+
+/// Reads the optional tolerance and iteration cap that follow the matrix argument.
+fn convergence_arg(name: &str, args: &[Value]) -> Result<Convergence, Error> {
+    let mut conv = Convergence::default();
+
+    if args.is_empty() || args.len() > 3 {
+        return Err(Error::runtime(format!("{} expects 1 to 3 argument(s), got {}", name, args.len()))
+            .with_code(ErrorCode::ArityMismatch));
+    }
+
+    if let Some(tolerance) = args.get(1) {
+        conv.tolerance = positive_tolerance(name, tolerance)?;
+    }
+
+    if let Some(iterations) = args.get(2) {
+        match iterations {
+            Value::Number(n) if *n > 0 => conv.max_iterations = *n as usize,
+            _ => return Err(Error::runtime(format!("{} expects a positive Number of iterations", name))
+                .with_code(ErrorCode::TypeMismatch)),
+        }
+    }
+
+    Ok(conv)
+}
+
+fn positive_tolerance(name: &str, value: &Value) -> Result<f64, Error> {
+    match value.as_f64() {
+        Some(tolerance) if tolerance > 0.0 => Ok(tolerance),
+        _ => Err(Error::runtime(format!("{} expects a positive tolerance", name)).with_code(ErrorCode::TypeMismatch)),
+    }
+}
+
+/// Reads `[M, tol?]`, returning the real matrix and the tolerance if one was given.
+fn tolerance_arg(name: &str, args: &[Value]) -> Result<(Dense<f64>, Option<f64>), Error> {
+    if args.is_empty() || args.len() > 2 {
+        return Err(Error::runtime(format!("{} expects 1 to 2 argument(s), got {}", name, args.len()))
+            .with_code(ErrorCode::ArityMismatch));
+    }
+
+    let mat = real_arg(name, matrix_arg(name, &args[0])?)?;
+    let tolerance = args.get(1).map(|tol| positive_tolerance(name, tol)).transpose()?;
+
+    Ok((mat, tolerance))
+}
+
+/// `lu(M[, tol])` returns `[L, U, P]` with `PM = LU`. Pivots at or below `tol` times the
+/// largest entry count as zero.
+pub fn lu(args: Vec<Value>) -> Result<Value, Error> {
+    let lu = match tolerance_arg("lu", &args)? {
+        (mat, Some(tol)) => Lu::with_tolerance(&mat, tol * largest(&mat))?,
+        (mat, None) => Lu::new(&mat)?,
+    };
+
+    Ok(Value::Array(vec![
+        decimal(lu.lower()),
+        decimal(lu.upper()),
+        Value::Matrix(Matrix::Number(lu.permutation())),
+    ]))
+}
+
+/// `qr(M[, tol])` returns `[Q, R]` with `M = QR`. Columns at or below `tol` times the largest
+/// entry are skipped instead of reflected.
+pub fn qr(args: Vec<Value>) -> Result<Value, Error> {
+    let (mat, tol) = tolerance_arg("qr", &args)?;
+    let (q, r) = qr_of(&mat, tol.unwrap_or(0.0) * largest(&mat));
+
+    Ok(Value::Array(vec![decimal(q), decimal(r)]))
+}
+
+/// `chol(M[, tol])` returns the lower triangular `L` with `M = L * trn(L)`.
+pub fn chol(args: Vec<Value>) -> Result<Value, Error> {
+    let (mat, tol) = tolerance_arg("chol", &args)?;
+
+    Ok(decimal(chol_of(&mat, tol.unwrap_or(Convergence::default().tolerance))?))
+}
+
+/// `eig(M[, tol[, iterations]])` returns `[values, vectors]`, one eigenvector per column.
+pub fn eig(args: Vec<Value>) -> Result<Value, Error> {
+    let conv = convergence_arg("eig", &args)?;
+    let eigen = eig_of(&real_arg("eig", matrix_arg("eig", &args[0])?)?, &conv)?;
+
+    Ok(Value::Array(match eigen {
+        Eigen::Real(values, vectors) => vec![decimals(values), decimal(vectors)],
+        Eigen::Complex(values, vectors) => vec![
+            Value::Array(values.into_iter().map(Value::Complex).collect()),
+            Value::Matrix(Matrix::Complex(vectors)),
+        ],
+    }))
+}
+
+/// `svd(M[, tol[, iterations]])` returns `[U, S, V]` with `M = U * diag(S) * trn(V)`.
+pub fn svd(args: Vec<Value>) -> Result<Value, Error> {
+    let conv = convergence_arg("svd", &args)?;
+    let Svd { u, s, v } = svd_of(&real_arg("svd", matrix_arg("svd", &args[0])?)?, &conv)?;
+
+    Ok(Value::Array(vec![decimal(u), decimals(s), decimal(v)]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EPS: f64 = 1e-9;
+
+    fn dense(rows: usize, cols: usize, data: &[f64]) -> Dense<f64> {
+        Dense::new(rows, cols, data.to_vec()).unwrap()
+    }
+
+    fn diag(values: &[f64]) -> Dense<f64> {
+        Dense::from_fn(values.len(), values.len(), |i, j| if i == j { values[i] } else { 0.0 })
+    }
+
+    fn assert_close(a: &Dense<f64>, b: &Dense<f64>) {
+        let diff = a.data().iter().zip(b.data()).fold(0.0_f64, |max, (x, y)| max.max((x - y).abs()));
+
+        assert!(diff < EPS, "{:?} != {:?}", a, b);
+    }
+
+    #[test]
+    fn symmetric_eig_reconstructs() {
+        let a = dense(3, 3, &[2.0, 1.0, 0.0, 1.0, 3.0, 1.0, 0.0, 1.0, 4.0]);
+
+        let (values, vectors) = match eig_of(&a, &Convergence::default()).unwrap() {
+            Eigen::Real(values, vectors) => (values, vectors),
+            Eigen::Complex(..) => panic!("a symmetric matrix has real eigenvalues"),
+        };
+
+        assert!(values.windows(2).all(|w| w[0] <= w[1]));
+        assert_close(&a.matmul(&vectors).unwrap(), &vectors.matmul(&diag(&values)).unwrap());
+        assert_close(&vectors.transpose().matmul(&vectors).unwrap(), &identity(3));
+    }
+
+    #[test]
+    fn hessenberg_qr_finds_real_eigenvalues() {
+        // companion matrix of (x - 1)(x - 2)(x - 3)
+        let a = dense(3, 3, &[0.0, 0.0, 6.0, 1.0, 0.0, -11.0, 0.0, 1.0, 6.0]);
+
+        let (values, vectors) = match eig_of(&a, &Convergence::default()).unwrap() {
+            Eigen::Real(values, vectors) => (values, vectors),
+            Eigen::Complex(..) => panic!("expected real eigenvalues"),
+        };
+
+        for (value, expected) in values.iter().zip([1.0, 2.0, 3.0]) {
+            assert!((value - expected).abs() < EPS, "{:?}", values);
+        }
+
+        assert_close(&a.matmul(&vectors).unwrap(), &vectors.matmul(&diag(&values)).unwrap());
+    }
+
+    #[test]
+    fn hessenberg_qr_finds_complex_pairs() {
+        let a = dense(3, 3, &[0.0, -1.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 2.0]);
+
+        let (values, vectors) = match eig_of(&a, &Convergence::default()).unwrap() {
+            Eigen::Complex(values, vectors) => (values, vectors),
+            Eigen::Real(..) => panic!("a rotation has complex eigenvalues"),
+        };
+
+        let expected = [Complex::new(0.0, -1.0), Complex::new(0.0, 1.0), Complex::new(2.0, 0.0)];
+
+        for (value, expected) in values.iter().zip(expected) {
+            assert!((*value - expected).abs() < EPS, "{:?}", values);
+        }
+
+        let av = a.map(Complex::from).matmul(&vectors).unwrap();
+
+        for (j, value) in values.iter().enumerate() {
+            for i in 0..3 {
+                assert!((av.get(i, j) - *value * vectors.get(i, j)).abs() < EPS);
+            }
+        }
+    }
+
+    #[test]
+    fn jacobi_svd_reconstructs() {
+        let tall = dense(3, 2, &[1.0, 2.0, 3.0, 4.0, 5.0, 6.0]);
+
+        for a in [tall.clone(), tall.transpose()] {
+            let Svd { u, s, v } = svd_of(&a, &Convergence::default()).unwrap();
+
+            assert!(s.windows(2).all(|w| w[0] >= w[1]));
+            assert_close(&u.matmul(&diag(&s)).unwrap().matmul(&v.transpose()).unwrap(), &a);
+        }
+    }
+
+    #[test]
+    fn iteration_cap_is_respected() {
+        let a = dense(3, 3, &[0.0, 0.0, 6.0, 1.0, 0.0, -11.0, 0.0, 1.0, 6.0]);
+        let conv = Convergence { max_iterations: 1, ..Convergence::default() };
+
+        assert_eq!(eig_of(&a, &conv).err().unwrap().code(), ErrorCode::NoConvergence);
+        assert_eq!(svd_of(&a, &conv).err().unwrap().code(), ErrorCode::NoConvergence);
+
+        let args = [Value::Matrix(Matrix::Decimal(a)), Value::Decimal(1e-9), Value::Number(0)];
+        assert_eq!(convergence_arg("eig", &args).unwrap_err().code(), ErrorCode::TypeMismatch);
+    }
+
+    #[test]
+    fn constructors_refuse_huge_matrices() {
+        assert!(matches!(zeros(vec![Value::Number(2), Value::Number(3)]), Ok(Value::Matrix(m)) if (m.rows(), m.cols()) == (2, 3)));
+        assert_eq!(zeros(vec![Value::Number(4611686018427387904), Value::Number(4)]).unwrap_err().code(), ErrorCode::InvalidOperation);
+        assert_eq!(ones(vec![Value::Number(i64::MAX), Value::Number(i64::MAX)]).unwrap_err().code(), ErrorCode::InvalidOperation);
+        assert_eq!(eye(vec![Value::Number(1_000_000)]).unwrap_err().code(), ErrorCode::InvalidOperation);
+        assert_eq!(eye(vec![Value::Number(0)]).unwrap_err().code(), ErrorCode::TypeMismatch);
+    }
+
+    #[test]
+    fn chol_rejects_unsuitable_matrices() {
+        let a = dense(2, 2, &[4.0, 2.0, 2.0, 3.0]);
+        let l = chol_of(&a, 1e-12).unwrap();
+
+        assert_close(&l.matmul(&l.transpose()).unwrap(), &a);
+        assert_eq!(chol_of(&dense(2, 2, &[1.0, 2.0, 0.0, 1.0]), 1e-12).unwrap_err().code(), ErrorCode::InvalidOperation);
+        assert_eq!(chol_of(&dense(2, 2, &[1.0, 2.0, 2.0, 1.0]), 1e-12).unwrap_err().code(), ErrorCode::InvalidOperation);
+    }
+
+    #[test]
+    fn decompositions_take_a_tolerance() {
+        let a = dense(2, 2, &[1.0, 0.0, 0.0, 1e-9]);
+
+        assert!(!Lu::new(&a).unwrap().is_singular());
+        assert!(Lu::with_tolerance(&a, 1e-6).unwrap().is_singular());
+
+        let nearly = dense(2, 2, &[4.0, 2.0 + 1e-9, 2.0, 3.0]);
+
+        assert!(chol_of(&nearly, 1e-12).is_err());
+        assert!(chol_of(&nearly, 1e-6).is_ok());
+
+        let m = Value::Matrix(Matrix::Decimal(a));
+
+        assert!(qr(vec![m.clone(), Value::Decimal(1e-6)]).is_ok());
+        assert_eq!(lu(vec![m.clone(), Value::Number(-1)]).unwrap_err().code(), ErrorCode::TypeMismatch);
+        assert_eq!(chol(vec![m.clone(), Value::Decimal(1e-6), Value::Number(1)]).unwrap_err().code(), ErrorCode::ArityMismatch);
+    }
+}
@@ -1,21 +1,63 @@
-mod error;
-mod lexer;
-mod ast;
-mod parser;
-mod memory;
-mod interpreter;
-
 use rustyline::error::ReadlineError;
 use rustyline::{Editor};
-use crate::lexer::Lexer;
-use crate::parser::Parser;
-use crate::interpreter::{Interpreter};
-use crate::memory::{Value};
+use jmath::diagnostics::{self, Style};
+use jmath::error::Error;
+use jmath::lexer::Lexer;
+use jmath::parser::Parser;
+use jmath::interpreter::{Interpreter, Overflow};
+use jmath::memory::{Value};
+
+fn eval(interpreter: &mut Interpreter, source: &str, start: usize) -> Result<Option<Value>, Error> {
+    let mut lexer = Lexer::from_position(source, start);
+    let mut parser = Parser::new(&mut lexer);
+
+    let nodes = parser.run()?;
+    let mut value = None;
+
+    for node in nodes {
+        value = Some(interpreter.visit(&node)?);
+    }
+
+    Ok(value)
+}
+
+/// Describes a function for `help name`: its signature and doc comment.
+fn help(interpreter: &Interpreter, name: &str) -> String {
+    match interpreter.lookup(name) {
+        Some(Value::Function(function)) => match &function.doc {
+            Some(doc) => format!("{}\n{}", Value::Function(function.clone()), doc),
+            None => format!("{}\nNo documentation; add `///` comments before `fn {}`", Value::Function(function.clone()), name),
+        },
+        Some(Value::NativeFunction(_)) => format!("{} is a built-in function", name),
+        Some(value) => format!("{} is a {}", name, value.kind()),
+        None => format!("{} is not defined", name),
+    }
+}
+
+/// Handles `overflow mode`, which sets what happens when `Number` arithmetic
+/// overflows 64 bits.
+fn set_overflow(interpreter: &mut Interpreter, mode: &str) -> String {
+    let overflow = match mode {
+        "checked" => Overflow::Checked,
+        "promote" => Overflow::Promote,
+        "widen" => Overflow::Widen,
+        other => return format!("Unknown overflow mode '{}'; use checked, promote or widen", other),
+    };
+
+    interpreter.set_overflow(overflow);
+
+    format!("Overflow is now {}", mode)
+}
 
 fn repl() -> Result<(), Box<dyn std::error::Error>> {
     let mut rl = Editor::<()>::new().expect("Failed to initialize CLI");
     let mut interpreter = Interpreter::new();
 
+    // every line is lexed against the whole session, so errors raised inside
+    // functions declared on earlier lines still point at the right source
+    let mut history = String::new();
+    let mut pending_doc = None;
+
     loop {
         let readline = rl.readline("jmath> ");
 
@@ -25,21 +67,36 @@ fn repl() -> Result<(), Box<dyn std::error::Error>> {
                     break
                 }
 
-                let mut lexer = Lexer::new(line.as_str());
-                let mut parser = Parser::new(&mut lexer);
+                if let Some(name) = line.trim().strip_prefix("help ") {
+                    println!("{}", help(&interpreter, name.trim()));
+
+                    continue;
+                }
 
-                let nodes = parser.run()?;
-                let mut value: Value = Value::Number(0);
+                // two bare words are not valid jmath, so this cannot shadow an expression
+                if let ["overflow", mode] = line.split_whitespace().collect::<Vec<_>>().as_slice() {
+                    println!("{}", set_overflow(&mut interpreter, mode));
 
-                if nodes.len() == 0 {
                     continue;
                 }
 
-                for node in nodes {
-                    value = interpreter.visit(&node)?
+                let start = *pending_doc.get_or_insert(history.len());
+
+                history.push_str(&line);
+                history.push('\n');
+
+                // doc comments are kept until the line they document is entered
+                if line.trim_start().starts_with("///") {
+                    continue;
                 }
 
-                println!("{}", value)
+                pending_doc = None;
+
+                match eval(&mut interpreter, &history, start) {
+                    Ok(Some(value)) => println!("{}", value),
+                    Ok(None) => {},
+                    Err(err) => println!("{}", diagnostics::render(&err, &history, Style::Ansi)),
+                }
             },
             Err(ReadlineError::Interrupted) | Err(ReadlineError::Eof) => break,
             Err(err) => return Result::Err(Box::new(err))
@@ -50,10 +107,7 @@ fn repl() -> Result<(), Box<dyn std::error::Error>> {
 }
 
 fn main() {
-    let res = repl();
-
-    if res.is_err() {
-        println!("{}", res.err().unwrap());
-        main();
+    if let Err(err) = repl() {
+        println!("{}", err);
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,322 @@
+use std::cell::Cell;
+use std::cmp::Ordering;
+use std::f64::consts;
+use crate::ast::Operator;
+use crate::bigint::BigInt;
+use crate::complex::Complex;
+use crate::error::{Error, ErrorCode};
+use crate::linalg::expect_arity;
+use crate::matrix::Matrix;
+use crate::memory::{broadcast_with, ActivationRecord, Value};
+use crate::rational::Rational;
+use crate::stats;
+
+/// How math natives treat arguments outside their domain, such as `sqrt(-1)`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Domain {
+    /// Raise a runtime error.
+    #[default]
+    Strict,
+    /// Return NaN.
+    Permissive,
+}
+
+thread_local! {
+    static DOMAIN: Cell<Domain> = const { Cell::new(Domain::Strict) };
+}
+
+/// Runs `f` with `domain` in effect for the math natives it calls.
+pub fn with_domain<T>(domain: Domain, f: impl FnOnce() -> T) -> T {
+    let prev = DOMAIN.replace(domain);
+    let res = f();
+
+    DOMAIN.set(prev);
+
+    res
+}
+
+fn outside_domain(name: &str, arg: impl std::fmt::Display) -> Result<Value, Error> {
+    match DOMAIN.get() {
+        Domain::Permissive => Ok(Value::Decimal(f64::NAN)),
+        Domain::Strict => Err(Error::runtime(format!("{}: {} is outside the domain", name, arg))
+            .with_code(ErrorCode::Domain)),
+    }
+}
+
+fn not_a_number(name: &str, value: &Value) -> Error {
+    Error::runtime(format!("{} expects a number, got {}", name, value.kind()))
+        .with_code(ErrorCode::TypeMismatch)
+}
+
+/// Applies `f` to every number in a scalar, array or matrix.
+fn map_numbers(name: &str, value: Value, f: &impl Fn(Value) -> Result<Value, Error>) -> Result<Value, Error> {
+    match value {
+        Value::Array(items) => items.into_iter()
+            .map(|item| map_numbers(name, item, f))
+            .collect::<Result<Vec<Value>, Error>>()
+            .map(Value::Array),
+        Value::Matrix(mat) => {
+            let rows = map_numbers(name, Value::Array(mat.to_rows()), f)?;
+
+            match &rows {
+                Value::Array(items) => match Matrix::from_values(items) {
+                    Some(mat) => mat.map(Value::Matrix),
+                    None => Ok(rows),
+                },
+                _ => Ok(rows),
+            }
+        },
+        value if value.is_scalar() => f(value),
+        other => Err(not_a_number(name, &other)),
+    }
+}
+
+/// Runs a one-argument native over numbers, element-wise over arrays and matrices.
+fn unary(name: &str, args: Vec<Value>, f: impl Fn(Value) -> Result<Value, Error>) -> Result<Value, Error> {
+    expect_arity(name, &args, 1)?;
+
+    map_numbers(name, args.into_iter().next().unwrap_or(Value::Number(0)), &f)
+}
+
+fn complex_part(value: &Value) -> Complex {
+    value.as_complex().unwrap_or_default()
+}
+
+pub fn re(args: Vec<Value>) -> Result<Value, Error> {
+    unary("re", args, |value| match value {
+        Value::Complex(z) => Ok(Value::Decimal(z.re)),
+        real => Ok(real),
+    })
+}
+
+pub fn im(args: Vec<Value>) -> Result<Value, Error> {
+    unary("im", args, |value| match value {
+        Value::Complex(z) => Ok(Value::Decimal(z.im)),
+        _ => Ok(Value::Number(0)),
+    })
+}
+
+pub fn abs(args: Vec<Value>) -> Result<Value, Error> {
+    unary("abs", args, |value| match value {
+        Value::Complex(z) => Ok(Value::Decimal(z.abs())),
+        Value::Decimal(x) => Ok(Value::Decimal(x.abs())),
+        Value::Number(x) if x < 0 => -Value::Number(x),
+        Value::BigInt(x) => Ok(Value::from_bigint(x.abs())),
+        Value::Rational(x) if x.numer().is_negative() => -Value::Rational(x),
+        other => Ok(other),
+    })
+}
+
+pub fn arg(args: Vec<Value>) -> Result<Value, Error> {
+    unary("arg", args, |value| Ok(Value::Decimal(complex_part(&value).arg())))
+}
+
+pub fn conj(args: Vec<Value>) -> Result<Value, Error> {
+    unary("conj", args, |value| match value {
+        Value::Complex(z) => Ok(Value::Complex(z.conj())),
+        real => Ok(real),
+    })
+}
+
+fn real_arg(name: &str, value: &Value) -> Result<f64, Error> {
+    match value {
+        Value::Complex(_) => Err(Error::runtime(format!("{} expects a real number, got Complex", name))
+            .with_code(ErrorCode::TypeMismatch)),
+        other => other.as_f64().ok_or_else(|| not_a_number(name, other)),
+    }
+}
+
+/// Runs a function that has both a real and a complex form. A real argument the
+/// real form rejects (such as `sqrt(-4)`) gives the complex principal value, and a
+/// NaN result from a non-NaN argument in both forms is a domain violation.
+fn elementary(name: &str, args: Vec<Value>, real: fn(f64) -> f64, complex: fn(Complex) -> Complex) -> Result<Value, Error> {
+    unary(name, args, |value| match value {
+        Value::Complex(z) => match complex(z) {
+            res if res.is_nan() && !z.is_nan() => outside_domain(name, z),
+            res => Ok(Value::Complex(res)),
+        },
+        other => {
+            let x = real_arg(name, &other)?;
+
+            match real(x) {
+                res if res.is_nan() && !x.is_nan() => match complex(Complex::from(x)) {
+                    z if z.is_nan() => outside_domain(name, x),
+                    z => Ok(Value::Complex(z)),
+                },
+                res => Ok(Value::Decimal(res)),
+            }
+        },
+    })
+}
+
+/// The logarithms are singular at zero; NaN there makes `elementary` report a
+/// domain error instead of returning `-inf`.
+fn ln_real(x: f64) -> f64 {
+    if x == 0.0 { f64::NAN } else { x.ln() }
+}
+
+fn ln_complex(z: Complex) -> Complex {
+    if z == Complex::default() { Complex::new(f64::NAN, f64::NAN) } else { z.ln() }
+}
+
+pub fn sin(args: Vec<Value>) -> Result<Value, Error> {
+    elementary("sin", args, f64::sin, Complex::sin)
+}
+
+pub fn cos(args: Vec<Value>) -> Result<Value, Error> {
+    elementary("cos", args, f64::cos, Complex::cos)
+}
+
+pub fn tan(args: Vec<Value>) -> Result<Value, Error> {
+    elementary("tan", args, f64::tan, Complex::tan)
+}
+
+pub fn exp(args: Vec<Value>) -> Result<Value, Error> {
+    elementary("exp", args, f64::exp, Complex::exp)
+}
+
+pub fn ln(args: Vec<Value>) -> Result<Value, Error> {
+    elementary("ln", args, ln_real, ln_complex)
+}
+
+pub fn log10(args: Vec<Value>) -> Result<Value, Error> {
+    elementary("log10", args, |x| if x == 0.0 { f64::NAN } else { x.log10() }, |z| ln_complex(z) / Complex::from(consts::LN_10))
+}
+
+pub fn sqrt(args: Vec<Value>) -> Result<Value, Error> {
+    elementary("sqrt", args, f64::sqrt, Complex::sqrt)
+}
+
+/// A whole decimal as a `Number` when it fits in 64 bits.
+fn integral(x: f64) -> Value {
+    if x.is_finite() && x >= i64::MIN as f64 && x < i64::MAX as f64 {
+        Value::Number(x as i64)
+    } else {
+        Value::Decimal(x)
+    }
+}
+
+fn rounding(
+    name: &str,
+    args: Vec<Value>,
+    real: fn(f64) -> f64,
+    exact: fn(&Rational) -> BigInt,
+) -> Result<Value, Error> {
+    unary(name, args, |value| match value {
+        Value::Decimal(x) => Ok(integral(real(x))),
+        Value::Rational(x) => Ok(Value::from_bigint(exact(&x))),
+        other => real_arg(name, &other).map(|_| other),
+    })
+}
+
+pub fn floor(args: Vec<Value>) -> Result<Value, Error> {
+    rounding("floor", args, f64::floor, |x| x.floor())
+}
+
+pub fn ceil(args: Vec<Value>) -> Result<Value, Error> {
+    rounding("ceil", args, f64::ceil, |x| x.ceil())
+}
+
+/// Rounds to the nearest integer, with halves away from zero.
+pub fn round(args: Vec<Value>) -> Result<Value, Error> {
+    rounding("round", args, f64::round, |x| x.round())
+}
+
+/// Floored modulo: the result takes the sign of the divisor, so `mod(-1, 3)` is 2.
+fn modulo(lhs: Value, rhs: Value) -> Result<Value, Error> {
+    match (lhs, rhs) {
+        (Value::Number(a), Value::Number(b)) => match a.checked_rem(b) {
+            _ if b == 0 => outside_domain("mod", "a divisor of 0"),
+            Some(r) if r != 0 && (r < 0) != (b < 0) => Ok(Value::Number(r + b)),
+            r => Ok(Value::Number(r.unwrap_or(0))),
+        },
+        (a, b) if a.is_exact() || b.is_exact() => match (a.to_rational(), b.to_rational()) {
+            (Some(a), Some(b)) => match a.checked_div(&b) {
+                Some(quotient) => Ok(Value::from_rational(&a - &(&b * &Rational::from_integer(quotient.floor())))),
+                None => outside_domain("mod", "a divisor of 0"),
+            },
+            _ => modulo(a.promote(), b.promote()),
+        },
+        (a, b) => {
+            let (x, y) = (real_arg("mod", &a)?, real_arg("mod", &b)?);
+
+            if y == 0.0 {
+                return outside_domain("mod", "a divisor of 0");
+            }
+
+            let r = x % y;
+
+            Ok(Value::Decimal(if r != 0.0 && (r < 0.0) != (y < 0.0) { r + y } else { r }))
+        },
+    }
+}
+
+fn binary(name: &str, lhs: Value, rhs: Value, f: &dyn Fn(Value, Value) -> Result<Value, Error>) -> Result<Value, Error> {
+    broadcast_with(lhs, name, rhs, &|x, y| {
+        for value in [&x, &y] {
+            if !value.is_scalar() {
+                return Err(not_a_number(name, value));
+            }
+        }
+
+        f(x, y)
+    })
+}
+
+pub fn modulus(args: Vec<Value>) -> Result<Value, Error> {
+    expect_arity("mod", &args, 2)?;
+
+    let mut args = args.into_iter();
+
+    binary("mod", args.next().unwrap_or(Value::Number(0)), args.next().unwrap_or(Value::Number(0)), &modulo)
+}
+
+/// Picks `a` or `b`, whichever compares as `wanted` (`Less` for min).
+fn pick(a: Value, b: Value, wanted: &Operator) -> Result<Value, Error> {
+    match b.clone().compare(wanted, a.clone())? {
+        Value::Bool(true) => Ok(b),
+        _ => Ok(a),
+    }
+}
+
+/// `min(a)` is the smallest entry of an array or matrix and `min(a, [], axis)` reduces
+/// along an axis; otherwise `min(a, b, ...)` compares its arguments element-wise,
+/// broadcasting arrays and matrices, so `min(a, 0)` clamps rather than reduces.
+fn extreme(name: &str, args: Vec<Value>, wanted: Operator) -> Result<Value, Error> {
+    let ordering = if matches!(wanted, Operator::Less) { Ordering::Less } else { Ordering::Greater };
+
+    match args.as_slice() {
+        [_] => return stats::extreme(name, args, ordering),
+        [_, Value::Array(none), _] if none.is_empty() => {
+            let mut args = args;
+            let axis = args.remove(2);
+
+            args[1] = axis;
+
+            return stats::extreme(name, args, ordering);
+        },
+        _ => {},
+    }
+
+    let mut args = args.into_iter();
+
+    match args.next() {
+        Some(first) => args.try_fold(first, |acc, arg| binary(name, acc, arg, &|a, b| pick(a, b, &wanted))),
+        None => Err(Error::runtime(format!("{} expects at least 1 argument(s), got 0", name))
+            .with_code(ErrorCode::ArityMismatch)),
+    }
+}
+
+pub fn min(args: Vec<Value>) -> Result<Value, Error> {
+    extreme("min", args, Operator::Less)
+}
+
+pub fn max(args: Vec<Value>) -> Result<Value, Error> {
+    extreme("max", args, Operator::Greater)
+}
+
+pub fn add_constants(ar: &mut ActivationRecord) {
+    ar.insert(String::from("pi"), Value::Decimal(consts::PI));
+    ar.insert(String::from("π"), Value::Decimal(consts::PI));
+    ar.insert(String::from("e"), Value::Decimal(consts::E));
+}
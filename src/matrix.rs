@@ -0,0 +1,583 @@
+use std::ops;
+use crate::ast::Operator;
+use crate::complex::Complex;
+use crate::error::{Error, ErrorCode};
+use crate::memory::{checked_int, overflow, Selector, Value};
+
+/// Row-major storage for a `rows` x `cols` matrix.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Dense<T> {
+    rows: usize,
+    cols: usize,
+    data: Vec<T>,
+}
+
+fn shape_mismatch(message: String) -> Error {
+    Error::runtime(message).with_code(ErrorCode::ShapeMismatch)
+}
+
+impl<T: Copy> Dense<T> {
+    pub fn new(rows: usize, cols: usize, data: Vec<T>) -> Result<Dense<T>, Error> {
+        if data.len() != rows * cols {
+            return Err(shape_mismatch(format!(
+                "Cannot build a {}x{} matrix from {} elements", rows, cols, data.len()
+            )));
+        }
+
+        Ok(Dense {
+            rows,
+            cols,
+            data,
+        })
+    }
+
+    pub fn from_fn(rows: usize, cols: usize, f: impl Fn(usize, usize) -> T) -> Dense<T> {
+        let mut data = Vec::with_capacity(rows * cols);
+
+        for i in 0..rows {
+            for j in 0..cols {
+                data.push(f(i, j));
+            }
+        }
+
+        Dense {
+            rows,
+            cols,
+            data,
+        }
+    }
+
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    pub fn cols(&self) -> usize {
+        self.cols
+    }
+
+    pub fn data(&self) -> &[T] {
+        &self.data
+    }
+
+    pub fn get(&self, i: usize, j: usize) -> T {
+        self.data[i * self.cols + j]
+    }
+
+    pub fn set(&mut self, i: usize, j: usize, value: T) {
+        self.data[i * self.cols + j] = value;
+    }
+
+    pub fn row(&self, i: usize) -> &[T] {
+        &self.data[i * self.cols..(i + 1) * self.cols]
+    }
+
+    pub fn map<U>(&self, f: impl Fn(T) -> U) -> Dense<U> {
+        Dense {
+            rows: self.rows,
+            cols: self.cols,
+            data: self.data.iter().map(|x| f(*x)).collect(),
+        }
+    }
+
+    pub fn try_map<U>(&self, f: impl Fn(T) -> Result<U, Error>) -> Result<Dense<U>, Error> {
+        Ok(Dense {
+            rows: self.rows,
+            cols: self.cols,
+            data: self.data.iter().map(|x| f(*x)).collect::<Result<_, _>>()?,
+        })
+    }
+
+    pub fn zip_with<U: Copy, V>(
+        &self,
+        other: &Dense<U>,
+        f: impl Fn(T, U) -> Result<V, Error>,
+    ) -> Result<Dense<V>, Error> {
+        if self.rows != other.rows || self.cols != other.cols {
+            return Err(shape_mismatch(format!(
+                "Cannot combine a {}x{} matrix with a {}x{} matrix", self.rows, self.cols, other.rows, other.cols
+            )));
+        }
+
+        Ok(Dense {
+            rows: self.rows,
+            cols: self.cols,
+            data: self.data.iter().zip(other.data.iter()).map(|(x, y)| f(*x, *y)).collect::<Result<_, _>>()?,
+        })
+    }
+
+    pub fn transpose(&self) -> Dense<T> {
+        Dense::from_fn(self.cols, self.rows, |i, j| self.get(j, i))
+    }
+
+    pub fn swap_rows(&mut self, a: usize, b: usize) {
+        if a != b {
+            for j in 0..self.cols {
+                self.data.swap(a * self.cols + j, b * self.cols + j);
+            }
+        }
+    }
+}
+
+/// Arithmetic on matrix entries; integers report overflow instead of wrapping.
+pub trait Entry: Copy + Default {
+    fn try_add(self, other: Self) -> Option<Self>;
+    fn try_mul(self, other: Self) -> Option<Self>;
+}
+
+impl Entry for i64 {
+    fn try_add(self, other: Self) -> Option<Self> {
+        self.checked_add(other)
+    }
+
+    fn try_mul(self, other: Self) -> Option<Self> {
+        self.checked_mul(other)
+    }
+}
+
+impl Entry for f64 {
+    fn try_add(self, other: Self) -> Option<Self> {
+        Some(self + other)
+    }
+
+    fn try_mul(self, other: Self) -> Option<Self> {
+        Some(self * other)
+    }
+}
+
+impl<T: Entry> Dense<T> {
+    pub fn matmul(&self, other: &Dense<T>) -> Result<Dense<T>, Error> {
+        if self.cols != other.rows {
+            return Err(shape_mismatch(format!(
+                "Cannot multiply a {}x{} matrix by a {}x{} matrix", self.rows, self.cols, other.rows, other.cols
+            )));
+        }
+
+        let mut data = vec![T::default(); self.rows * other.cols];
+
+        // i-k-j order walks both operands row by row
+        for i in 0..self.rows {
+            let out = &mut data[i * other.cols..(i + 1) * other.cols];
+
+            for (k, &a) in self.row(i).iter().enumerate() {
+                for (o, &b) in out.iter_mut().zip(other.row(k)) {
+                    *o = a.try_mul(b).and_then(|ab| o.try_add(ab)).ok_or_else(|| {
+                        Error::runtime("Integer overflow in matrix product").with_code(ErrorCode::Overflow)
+                    })?;
+                }
+            }
+        }
+
+        Ok(Dense {
+            rows: self.rows,
+            cols: other.cols,
+            data,
+        })
+    }
+}
+
+/// A dense numeric matrix, stored as integers until a decimal or complex entry
+/// requires otherwise.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Matrix {
+    Number(Dense<i64>),
+    Decimal(Dense<f64>),
+    Complex(Dense<Complex>),
+}
+
+/// Whether the operator maps integers to integers: `/` always produces decimals, and
+/// so does `^` unless every exponent is non-negative.
+fn keeps_integers(op: &Operator, exponents: &[i64]) -> bool {
+    match op {
+        Operator::Add | Operator::Sub | Operator::Mul => true,
+        Operator::Exp => exponents.iter().all(|x| *x >= 0),
+        _ => false,
+    }
+}
+
+fn scalar_i64(op: &Operator, lhs: i64, rhs: i64) -> Result<i64, Error> {
+    checked_int(op, lhs, rhs).ok_or_else(|| overflow(lhs, op, rhs))
+}
+
+fn scalar_f64(op: &Operator, lhs: f64, rhs: f64) -> f64 {
+    match op {
+        Operator::Add => lhs + rhs,
+        Operator::Sub => lhs - rhs,
+        Operator::Mul => lhs * rhs,
+        Operator::Div => lhs / rhs,
+        _ => lhs.powf(rhs),
+    }
+}
+
+fn scalar_complex(op: &Operator, lhs: Complex, rhs: Complex) -> Complex {
+    match op {
+        Operator::Add => lhs + rhs,
+        Operator::Sub => lhs - rhs,
+        Operator::Mul => lhs * rhs,
+        Operator::Div => lhs / rhs,
+        _ => lhs.pow(rhs),
+    }
+}
+
+impl Matrix {
+    /// Builds a matrix from array literal items when every item is a non-empty array
+    /// of numbers. Returns `None` for anything that is not shaped like a matrix, and
+    /// an error when the rows have different lengths.
+    pub fn from_values(items: &[Value]) -> Option<Result<Matrix, Error>> {
+        let mut rows = Vec::with_capacity(items.len());
+
+        for item in items.iter() {
+            match item {
+                Value::Array(row) if !row.is_empty() && row.iter().all(Value::is_scalar) => rows.push(row),
+                _ => return None,
+            }
+        }
+
+        let cols = rows.first()?.len();
+
+        if let Some(row) = rows.iter().find(|row| row.len() != cols) {
+            return Some(Err(shape_mismatch(format!(
+                "Matrix rows must have the same length, expected {} got {}", cols, row.len()
+            ))));
+        }
+
+        let values = rows.iter().flat_map(|row| row.iter());
+
+        if rows.iter().any(|row| row.iter().any(|value| matches!(value, Value::Complex(_)))) {
+            let data = values.map(|value| value.as_complex().unwrap_or_default()).collect();
+
+            Some(Ok(Matrix::Complex(Dense { rows: rows.len(), cols, data })))
+        } else if rows.iter().all(|row| row.iter().all(|value| matches!(value, Value::Number(_)))) {
+            let data = values.map(|value| match value {
+                Value::Number(x) => *x,
+                _ => 0,
+            }).collect();
+
+            Some(Ok(Matrix::Number(Dense { rows: rows.len(), cols, data })))
+        } else {
+            let data = values.map(|value| value.as_f64().unwrap_or(0.0)).collect();
+
+            Some(Ok(Matrix::Decimal(Dense { rows: rows.len(), cols, data })))
+        }
+    }
+
+    pub fn rows(&self) -> usize {
+        match self {
+            Matrix::Number(m) => m.rows(),
+            Matrix::Decimal(m) => m.rows(),
+            Matrix::Complex(m) => m.rows(),
+        }
+    }
+
+    pub fn cols(&self) -> usize {
+        match self {
+            Matrix::Number(m) => m.cols(),
+            Matrix::Decimal(m) => m.cols(),
+            Matrix::Complex(m) => m.cols(),
+        }
+    }
+
+    pub fn same_shape(&self, other: &Matrix) -> bool {
+        self.rows() == other.rows() && self.cols() == other.cols()
+    }
+
+    pub fn get(&self, i: usize, j: usize) -> Value {
+        match self {
+            Matrix::Number(m) => Value::Number(m.get(i, j)),
+            Matrix::Decimal(m) => Value::Decimal(m.get(i, j)),
+            Matrix::Complex(m) => Value::Complex(m.get(i, j)),
+        }
+    }
+
+    pub fn row(&self, i: usize) -> Value {
+        Value::Array((0..self.cols()).map(|j| self.get(i, j)).collect())
+    }
+
+    pub fn column(&self, j: usize) -> Value {
+        Value::Array((0..self.rows()).map(|i| self.get(i, j)).collect())
+    }
+
+    /// Converts the matrix back into an array of row arrays.
+    pub fn to_rows(&self) -> Vec<Value> {
+        (0..self.rows()).map(|i| self.row(i)).collect()
+    }
+
+    pub fn is_complex(&self) -> bool {
+        matches!(self, Matrix::Complex(_))
+    }
+
+    /// Returns a copy of the entries promoted to decimals; complex entries keep
+    /// only their real part.
+    pub fn to_decimal(&self) -> Dense<f64> {
+        match self {
+            Matrix::Number(m) => m.map(|x| x as f64),
+            Matrix::Decimal(m) => m.clone(),
+            Matrix::Complex(m) => m.map(|z| z.re),
+        }
+    }
+
+    /// Returns a copy of the entries promoted to complex numbers.
+    pub fn to_complex(&self) -> Dense<Complex> {
+        match self {
+            Matrix::Complex(m) => m.clone(),
+            other => other.to_decimal().map(Complex::from),
+        }
+    }
+
+    pub fn transpose(&self) -> Matrix {
+        match self {
+            Matrix::Number(m) => Matrix::Number(m.transpose()),
+            Matrix::Decimal(m) => Matrix::Decimal(m.transpose()),
+            Matrix::Complex(m) => Matrix::Complex(m.transpose()),
+        }
+    }
+
+    /// Selects the sub-matrix covering `rows` x `cols`.
+    pub fn slice(&self, rows: ops::Range<usize>, cols: ops::Range<usize>) -> Matrix {
+        let (r0, c0) = (rows.start, cols.start);
+
+        match self {
+            Matrix::Number(m) => Matrix::Number(Dense::from_fn(rows.len(), cols.len(), |i, j| m.get(r0 + i, c0 + j))),
+            Matrix::Decimal(m) => Matrix::Decimal(Dense::from_fn(rows.len(), cols.len(), |i, j| m.get(r0 + i, c0 + j))),
+            Matrix::Complex(m) => Matrix::Complex(Dense::from_fn(rows.len(), cols.len(), |i, j| m.get(r0 + i, c0 + j))),
+        }
+    }
+
+    /// Stores a number at `(i, j)`, promoting the matrix to decimals or complex
+    /// numbers if needed.
+    pub fn set(&mut self, i: usize, j: usize, value: &Value) -> Result<(), Error> {
+        match (&mut *self, value) {
+            (Matrix::Number(m), Value::Number(x)) => m.set(i, j, *x),
+            (Matrix::Complex(m), value) if value.is_scalar() => m.set(i, j, value.as_complex().unwrap_or_default()),
+            (_, Value::Complex(z)) => {
+                let mut promoted = self.to_complex();
+
+                promoted.set(i, j, *z);
+                *self = Matrix::Complex(promoted);
+            },
+            (Matrix::Decimal(m), value) if value.is_scalar() => m.set(i, j, value.as_f64().unwrap_or(0.0)),
+            (Matrix::Number(m), value) if value.is_scalar() => {
+                let mut promoted = m.map(|x| x as f64);
+
+                promoted.set(i, j, value.as_f64().unwrap_or(0.0));
+                *self = Matrix::Decimal(promoted);
+            },
+            (_, other) => return Err(Error::runtime(format!("Matrix entries must be numbers, got {}", other.kind()))
+                .with_code(ErrorCode::TypeMismatch)),
+        }
+
+        Ok(())
+    }
+
+    /// Applies an arithmetic operator between every entry and a scalar. `scalar_left`
+    /// puts the scalar on the left-hand side, e.g. `2 - M`.
+    pub fn scalar_op(&self, op: &Operator, scalar: &Value, scalar_left: bool) -> Result<Matrix, Error> {
+        match (self, scalar) {
+            (Matrix::Number(m), Value::Number(x))
+                if keeps_integers(op, if scalar_left { m.data() } else { std::slice::from_ref(x) }) => {
+                let x = *x;
+
+                m.try_map(|y| {
+                    let (lhs, rhs) = if scalar_left { (x, y) } else { (y, x) };
+
+                    scalar_i64(op, lhs, rhs)
+                }).map(Matrix::Number)
+            },
+            (Matrix::Complex(_), _) | (_, Value::Complex(_)) => {
+                let x = scalar.as_complex().unwrap_or_default();
+
+                Ok(Matrix::Complex(self.to_complex().map(|y| {
+                    let (lhs, rhs) = if scalar_left { (x, y) } else { (y, x) };
+
+                    scalar_complex(op, lhs, rhs)
+                })))
+            },
+            _ => {
+                let x = scalar.as_f64().unwrap_or(f64::NAN);
+
+                Ok(Matrix::Decimal(self.to_decimal().map(|y| {
+                    let (lhs, rhs) = if scalar_left { (x, y) } else { (y, x) };
+
+                    scalar_f64(op, lhs, rhs)
+                })))
+            },
+        }
+    }
+
+    /// Applies an arithmetic operator entry by entry; both shapes must match.
+    pub fn elementwise(&self, op: &Operator, rhs: &Matrix) -> Result<Matrix, Error> {
+        match (self, rhs) {
+            (Matrix::Number(a), Matrix::Number(b)) if keeps_integers(op, b.data()) => {
+                a.zip_with(b, |x, y| scalar_i64(op, x, y)).map(Matrix::Number)
+            },
+            _ if self.is_complex() || rhs.is_complex() => {
+                self.to_complex().zip_with(&rhs.to_complex(), |x, y| Ok(scalar_complex(op, x, y))).map(Matrix::Complex)
+            },
+            _ => self.to_decimal().zip_with(&rhs.to_decimal(), |x, y| Ok(scalar_f64(op, x, y))).map(Matrix::Decimal),
+        }
+    }
+
+    pub fn matmul(&self, rhs: &Matrix) -> Result<Matrix, Error> {
+        match (self, rhs) {
+            (Matrix::Number(a), Matrix::Number(b)) => a.matmul(b).map(Matrix::Number),
+            _ if self.is_complex() || rhs.is_complex() => self.to_complex().matmul(&rhs.to_complex()).map(Matrix::Complex),
+            _ => self.to_decimal().matmul(&rhs.to_decimal()).map(Matrix::Decimal),
+        }
+    }
+
+    /// Raises a square matrix to a non-negative integer power by repeated squaring.
+    pub fn pow(&self, mut exp: u64) -> Result<Matrix, Error> {
+        let n = self.rows();
+
+        if self.cols() != n {
+            return Err(Error::runtime(format!(
+                "Matrix power needs a square matrix, got {}x{}; use .^ for an element-wise power", n, self.cols()
+            )).with_code(ErrorCode::ShapeMismatch));
+        }
+
+        let mut base = self.clone();
+        let mut res = Matrix::Number(Dense::from_fn(n, n, |i, j| (i == j) as i64));
+
+        while exp > 0 {
+            if exp & 1 == 1 {
+                res = res.matmul(&base)?;
+            }
+
+            exp >>= 1;
+
+            if exp > 0 {
+                base = base.matmul(&base)?;
+            }
+        }
+
+        Ok(res)
+    }
+
+    /// Wraps a vector as a `1 x n` (`column == false`) or `n x 1` matrix.
+    pub fn from_vector(items: &[Value], column: bool) -> Result<Matrix, Error> {
+        if items.is_empty() || !items.iter().all(Value::is_scalar) {
+            return Err(Error::runtime("Expected a non-empty array of numbers")
+                .with_code(ErrorCode::TypeMismatch));
+        }
+
+        let rows = if column {
+            items.iter().map(|item| Value::Array(vec![item.clone()])).collect::<Vec<_>>()
+        } else {
+            vec![Value::Array(items.to_vec())]
+        };
+
+        Matrix::from_values(&rows).unwrap_or_else(|| Err(Error::runtime("Invalid vector")))
+    }
+
+    /// Indexes by row then column: `M[i]` is a row, `M[:, j]` a column, `M[i, j]`
+    /// an entry and range selectors on both axes give a sub-matrix.
+    pub fn index(&self, selectors: &[Selector]) -> Result<Value, Error> {
+        let (rs, cs) = match selectors {
+            [] => return Ok(Value::Matrix(self.clone())),
+            [rs] => (rs, &Selector::All),
+            [rs, cs] => (rs, cs),
+            [rs, cs, rest @ ..] => return self.index(&[*rs, *cs])?.index(rest),
+        };
+
+        let rows = rs.bounds(self.rows())?;
+        let cols = cs.bounds(self.cols())?;
+
+        match (rs, cs) {
+            (Selector::At(_), Selector::At(_)) => Ok(self.get(rows.start, cols.start)),
+            (Selector::At(_), _) => Ok(Value::Array(cols.map(|j| self.get(rows.start, j)).collect())),
+            (_, Selector::At(_)) => Ok(Value::Array(rows.map(|i| self.get(i, cols.start)).collect())),
+            _ => Ok(Value::Matrix(self.slice(rows, cols))),
+        }
+    }
+
+    /// Assigns a number to every selected entry, or copies an array or matrix with
+    /// the same shape as the selection into it.
+    pub fn assign_index(&mut self, selectors: &[Selector], value: Value) -> Result<(), Error> {
+        let (rs, cs) = match selectors {
+            [rs] => (rs, &Selector::All),
+            [rs, cs] => (rs, cs),
+            _ => return Err(Error::runtime("Matrices take one or two indices")
+                .with_code(ErrorCode::IndexOutOfBounds)),
+        };
+
+        let rows = rs.bounds(self.rows())?;
+        let cols = cs.bounds(self.cols())?;
+        let (height, width) = (rows.len(), cols.len());
+
+        let source = match value {
+            Value::Array(items) => match (rs, cs) {
+                (Selector::At(_), _) if items.len() == width => Matrix::from_vector(&items, false)?,
+                (_, Selector::At(_)) if items.len() == height => Matrix::from_vector(&items, true)?,
+                _ => Matrix::from_values(&items).unwrap_or_else(|| Err(shape_mismatch(String::from(
+                    "Cannot assign an array that does not match the selection"
+                ))))?,
+            },
+            Value::Matrix(m) => m,
+            scalar => {
+                for i in rows {
+                    for j in cols.clone() {
+                        self.set(i, j, &scalar)?;
+                    }
+                }
+
+                return Ok(());
+            },
+        };
+
+        if (source.rows(), source.cols()) != (height, width) {
+            return Err(shape_mismatch(format!(
+                "Cannot assign a {}x{} value to a {}x{} selection", source.rows(), source.cols(), height, width
+            )));
+        }
+
+        for (ii, i) in rows.enumerate() {
+            for (jj, j) in cols.clone().enumerate() {
+                self.set(i, j, &source.get(ii, jj))?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn numbers(rows: &[&[i64]]) -> Vec<Value> {
+        rows.iter().map(|row| Value::Array(row.iter().map(|&x| Value::Number(x)).collect())).collect()
+    }
+
+    #[test]
+    fn rows_of_numbers_build_a_matrix() {
+        let mat = Matrix::from_values(&numbers(&[&[1, 2, 3], &[4, 5, 6]])).unwrap().unwrap();
+
+        assert_eq!((mat.rows(), mat.cols()), (2, 3));
+        assert_eq!(mat.get(1, 2), Value::Number(6));
+        assert_eq!(mat.transpose().get(2, 1), Value::Number(6));
+
+        let mixed = vec![Value::Array(vec![Value::Number(1), Value::Decimal(0.5)])];
+        assert!(matches!(Matrix::from_values(&mixed), Some(Ok(Matrix::Decimal(_)))));
+    }
+
+    #[test]
+    fn ragged_or_non_numeric_rows_are_not_matrices() {
+        let ragged = Matrix::from_values(&numbers(&[&[1, 2], &[3]])).unwrap();
+        assert_eq!(ragged.unwrap_err().code(), ErrorCode::ShapeMismatch);
+
+        assert!(Matrix::from_values(&[Value::Number(1)]).is_none());
+        assert!(Matrix::from_values(&[Value::Array(vec![])]).is_none());
+        assert_eq!(Dense::new(2, 2, vec![1, 2, 3]).unwrap_err().code(), ErrorCode::ShapeMismatch);
+    }
+
+    #[test]
+    fn integer_products_are_checked() {
+        let a = Dense::new(2, 3, vec![1, 2, 3, 4, 5, 6]).unwrap();
+        let b = a.transpose();
+
+        assert_eq!(a.matmul(&b).unwrap().data(), &[14, 32, 32, 77]);
+        assert_eq!(a.matmul(&a).unwrap_err().code(), ErrorCode::ShapeMismatch);
+
+        let big = Dense::new(1, 1, vec![i64::MAX]).unwrap();
+        assert_eq!(big.matmul(&big).unwrap_err().code(), ErrorCode::Overflow);
+    }
+}
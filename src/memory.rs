@@ -1,47 +1,202 @@
+use std::cmp::Ordering;
 use std::collections::HashMap;
+use std::rc::Rc;
 use std::ops;
 use std::fmt;
-use crate::error::Error;
-use crate::interpreter::Interpreter;
+use crate::ast::{Block, Operator};
+use crate::bigint::BigInt;
+use crate::complex::Complex;
+use crate::error::{Error, ErrorCode};
+use crate::linalg;
+use crate::matrix::Matrix;
+use crate::rational::Rational;
+
+#[derive(Debug)]
+pub struct Parameter {
+    pub name: String,
+    pub kind: String,
+}
+
+#[derive(Debug)]
+pub struct Function {
+    pub name: String,
+    pub parameters: Vec<Parameter>,
+    pub body: Block,
+    pub doc: Option<String>,
+}
 
 #[derive(Debug, Clone)]
 pub enum Value {
     Number(i64),
     Decimal(f64),
+    BigInt(BigInt),
+    Rational(Rational),
+    Complex(Complex),
+    Bool(bool),
+    String(String),
     Array(Vec<Value>),
+    Matrix(Matrix),
+    Function(Rc<Function>),
     NativeFunction(fn(Vec<Value>) -> Result<Value, Error>)
 }
 
 impl Value {
-    pub(crate) fn as_array(&self) -> &Vec<Value> {
-        if let Value::Array(v) = self {
-            v
-        } else {
-            panic!("{}", Interpreter::error("Invalid array"))
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Value::Number(_) => "Number",
+            Value::Decimal(_) => "Decimal",
+            Value::BigInt(_) => "BigInt",
+            Value::Rational(_) => "Rational",
+            Value::Complex(_) => "Complex",
+            Value::Bool(_) => "Bool",
+            Value::String(_) => "String",
+            Value::Array(_) => "Array",
+            Value::Matrix(_) => "Matrix",
+            Value::Function(_) | Value::NativeFunction(_) => "Function",
+        }
+    }
+
+    pub fn is_scalar(&self) -> bool {
+        matches!(self, Value::Number(_) | Value::Decimal(_) | Value::Complex(_)) || self.is_exact()
+    }
+
+    pub fn is_complex(&self) -> bool {
+        matches!(self, Value::Complex(_))
+    }
+
+    /// Whether arithmetic on the value leaves the `Number`/`Decimal` fast paths.
+    fn is_extended(&self) -> bool {
+        self.is_exact() || self.is_complex()
+    }
+
+    /// Whether the value is a `BigInt` or a `Rational`.
+    pub fn is_exact(&self) -> bool {
+        matches!(self, Value::BigInt(_) | Value::Rational(_))
+    }
+
+    /// Narrows an integer to a `Number` when it fits in 64 bits.
+    pub fn from_bigint(value: BigInt) -> Value {
+        match value.to_i64() {
+            Some(value) => Value::Number(value),
+            None => Value::BigInt(value),
         }
     }
 
-    pub(crate) fn as_array_mut(&mut self) -> &mut Vec<Value> {
-        if let Value::Array(v) = self {
-            v
+    /// Narrows a fraction with a denominator of 1 to an integer.
+    pub fn from_rational(value: Rational) -> Value {
+        if value.is_integer() {
+            Value::from_bigint(value.numer().clone())
         } else {
-            panic!("{}", Interpreter::error("Invalid array"))
+            Value::Rational(value)
+        }
+    }
+
+    /// The value as an exact fraction, if it is an integer or a `Rational`.
+    pub fn to_rational(&self) -> Option<Rational> {
+        match self {
+            Value::Number(value) => Some(Rational::from_integer(BigInt::from(*value))),
+            Value::BigInt(value) => Some(Rational::from_integer(value.clone())),
+            Value::Rational(value) => Some(value.clone()),
+            _ => None,
+        }
+    }
+
+    /// Converts every `Number` inside the value to a `Rational`, so that arithmetic
+    /// on it stays exact. Matrices cannot hold fractions, so they are left as they
+    /// are and keep their usual decimal results.
+    pub fn exact(self) -> Value {
+        match self {
+            Value::Number(value) => Value::Rational(Rational::from_integer(BigInt::from(value))),
+            Value::Array(items) => Value::Array(items.into_iter().map(Value::exact).collect()),
+            other => other,
+        }
+    }
+
+    /// Converts every `Number` inside the value to a `BigInt`. Matrices cannot hold
+    /// BigInts, so integer matrices become decimal matrices instead, trading exact
+    /// entries for range.
+    pub fn widen(self) -> Value {
+        match self {
+            Value::Number(value) => Value::BigInt(BigInt::from(value)),
+            Value::Array(items) => Value::Array(items.into_iter().map(Value::widen).collect()),
+            Value::Matrix(mat) if !mat.is_complex() => Value::Matrix(Matrix::Decimal(mat.to_decimal())),
+            other => other,
+        }
+    }
+
+    /// The length of each nesting level, following the first element; empty for scalars.
+    pub fn shape(&self) -> Vec<usize> {
+        match self {
+            Value::Array(items) => {
+                let mut shape = vec![items.len()];
+
+                if let Some(first) = items.first() {
+                    shape.extend(first.shape());
+                }
+
+                shape
+            },
+            Value::Matrix(mat) => vec![mat.rows(), mat.cols()],
+            _ => Vec::new(),
+        }
+    }
+
+    /// Converts every `Number`, `BigInt` and `Rational` inside the value to a `Decimal`.
+    pub fn promote(self) -> Value {
+        match self {
+            Value::Number(value) => Value::Decimal(value as f64),
+            Value::BigInt(value) => Value::Decimal(value.to_f64()),
+            Value::Rational(value) => Value::Decimal(value.to_f64()),
+            Value::Array(items) => Value::Array(items.into_iter().map(Value::promote).collect()),
+            Value::Matrix(mat) if !mat.is_complex() => Value::Matrix(Matrix::Decimal(mat.to_decimal())),
+            other => other,
+        }
+    }
+
+    /// The value as a complex number, if it is numeric.
+    pub fn as_complex(&self) -> Option<Complex> {
+        match self {
+            Value::Complex(value) => Some(*value),
+            other => other.as_f64().map(Complex::from),
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Value::Number(value) => Some(*value as f64),
+            Value::Decimal(value) => Some(*value),
+            Value::BigInt(value) => Some(value.to_f64()),
+            Value::Rational(value) => Some(value.to_f64()),
+            _ => None,
         }
     }
 }
 
+/// Values print with ANSI colors for the REPL; the alternate form (`{:#}`) leaves
+/// them out. Strings print as-is, but quoted inside arrays.
 impl fmt::Display for Value {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (color, reset) = if f.alternate() { ("", "") } else { ("\x1b[33m", "\x1b[0m") };
+
         match self {
-            Value::Number(value) => write!(f, "\x1b[33m{}\x1b[0m", value),
-            Value::Decimal(value) => write!(f, "\x1b[33m{}\x1b[0m", value),
+            Value::Number(value) => write!(f, "{}{}{}", color, value, reset),
+            Value::Decimal(value) => write!(f, "{}{}{}", color, value, reset),
+            Value::BigInt(value) => write!(f, "{}{}{}", color, value, reset),
+            Value::Rational(value) => write!(f, "{}{}{}", color, value, reset),
+            Value::Complex(value) => write!(f, "{}{}{}", color, value, reset),
+            Value::Bool(value) => write!(f, "{}{}{}", color, value, reset),
+            Value::String(value) => write!(f, "{}", value),
             Value::Array(value) => {
                 write!(f, "[")?;
 
                 let len = value.len();
 
                 for (i, item) in value.iter().enumerate() {
-                    write!(f, "{}", item)?;
+                    match item {
+                        Value::String(item) => write!(f, "{:?}", item)?,
+                        item if f.alternate() => write!(f, "{:#}", item)?,
+                        item => write!(f, "{}", item)?,
+                    }
 
                     if i < len - 1 {
                         write!(f, ", ")?;
@@ -50,382 +205,857 @@ impl fmt::Display for Value {
 
                 write!(f, "]")
             },
+            Value::Matrix(mat) if f.alternate() => write!(f, "{:#}", Value::Array(mat.to_rows())),
+            Value::Matrix(mat) => write!(f, "{}", Value::Array(mat.to_rows())),
+            Value::Function(function) => {
+                write!(f, "fn {}(", function.name)?;
+
+                let len = function.parameters.len();
+
+                for (i, param) in function.parameters.iter().enumerate() {
+                    write!(f, "{}: {}", param.name, param.kind)?;
+
+                    if i < len - 1 {
+                        write!(f, ", ")?;
+                    }
+                }
+
+                write!(f, ")")
+            },
             Value::NativeFunction(_) => write!(f, "NativeFunction"),
         }
     }
 }
 
+impl PartialEq for Value {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (lhs, rhs) if lhs.is_complex() || rhs.is_complex() => match (lhs.as_complex(), rhs.as_complex()) {
+                (Some(lhs), Some(rhs)) => lhs == rhs,
+                _ => false,
+            },
+            (lhs, rhs) if lhs.is_exact() || rhs.is_exact() => match (lhs.to_rational(), rhs.to_rational()) {
+                (Some(lhs), Some(rhs)) => lhs == rhs,
+                _ => lhs.is_scalar() && rhs.is_scalar() && lhs.as_f64() == rhs.as_f64(),
+            },
+            (Value::Number(lhs), Value::Number(rhs)) => lhs == rhs,
+            (Value::Number(lhs), Value::Decimal(rhs)) => (*lhs as f64) == *rhs,
+            (Value::Decimal(lhs), Value::Number(rhs)) => *lhs == (*rhs as f64),
+            (Value::Decimal(lhs), Value::Decimal(rhs)) => lhs == rhs,
+            (Value::Bool(lhs), Value::Bool(rhs)) => lhs == rhs,
+            (Value::String(lhs), Value::String(rhs)) => lhs == rhs,
+            (Value::Array(lhs), Value::Array(rhs)) => lhs == rhs,
+            (Value::Matrix(lhs), Value::Matrix(rhs)) => lhs.to_complex() == rhs.to_complex(),
+            (Value::Matrix(lhs), Value::Array(rhs)) => &lhs.to_rows() == rhs,
+            (Value::Array(lhs), Value::Matrix(rhs)) => lhs == &rhs.to_rows(),
+            (Value::Function(lhs), Value::Function(rhs)) => Rc::ptr_eq(lhs, rhs),
+            (Value::NativeFunction(lhs), Value::NativeFunction(rhs)) => std::ptr::fn_addr_eq(*lhs, *rhs),
+            _ => false,
+        }
+    }
+}
+
+fn invalid_operation(lhs: &str, op: &str, rhs: &str) -> Error {
+    Error::runtime(format!("Cannot apply {} to {} and {}", op, lhs, rhs))
+        .with_code(ErrorCode::InvalidOperation)
+}
+
+pub(crate) fn overflow(lhs: i64, op: &Operator, rhs: i64) -> Error {
+    Error::runtime(format!("Integer overflow in {} {} {}", lhs, op, rhs))
+        .with_code(ErrorCode::Overflow)
+}
+
+fn negation_overflow(value: i64) -> Error {
+    Error::runtime(format!("Integer overflow in -({})", value)).with_code(ErrorCode::Overflow)
+}
+
+fn checked_neg(value: i64) -> Result<i64, Error> {
+    value.checked_neg().ok_or_else(|| negation_overflow(value))
+}
+
+/// Integer `+`, `-`, `*` or `^` (with a non-negative exponent), or `None` on overflow.
+pub(crate) fn checked_int(op: &Operator, lhs: i64, rhs: i64) -> Option<i64> {
+    match op {
+        Operator::Add => lhs.checked_add(rhs),
+        Operator::Sub => lhs.checked_sub(rhs),
+        Operator::Mul => lhs.checked_mul(rhs),
+        Operator::Exp => u32::try_from(rhs).ok().and_then(|rhs| lhs.checked_pow(rhs)),
+        _ => None,
+    }
+}
+
+fn int_op(op: &Operator, lhs: i64, rhs: i64) -> Result<Value, Error> {
+    checked_int(op, lhs, rhs)
+        .map(Value::Number)
+        .ok_or_else(|| overflow(lhs, op, rhs))
+}
+
+impl Value {
+    fn is_matrix(&self) -> bool {
+        matches!(self, Value::Matrix(_))
+    }
+
+    /// Arithmetic where at least one operand is a matrix. `*` between matrices (or a
+    /// matrix and a vector) is the matrix product and `M^n` the matrix power; `+` and
+    /// `-` work entry-wise and broadcast operands of different shapes. A matrix can
+    /// only be divided by a scalar: entry-wise division is spelled `./`.
+    fn matrix_op(op: &Operator, lhs: Value, rhs: Value) -> Result<Value, Error> {
+        match (op, lhs, rhs) {
+            (Operator::Mul, Value::Matrix(a), Value::Matrix(b)) => a.matmul(&b).map(Value::Matrix),
+            (Operator::Exp, Value::Matrix(a), exp) => Value::matrix_power(a, &exp),
+            (Operator::Add | Operator::Sub, Value::Matrix(a), Value::Matrix(b)) if a.same_shape(&b) => {
+                a.elementwise(op, &b).map(Value::Matrix)
+            },
+            (_, Value::Matrix(a), scalar) if scalar.is_scalar() => a.scalar_op(op, &scalar, false).map(Value::Matrix),
+            (Operator::Add | Operator::Sub | Operator::Mul, scalar, Value::Matrix(b)) if scalar.is_scalar() => {
+                b.scalar_op(op, &scalar, true).map(Value::Matrix)
+            },
+            (Operator::Mul, Value::Matrix(a), Value::Array(v)) => {
+                let res = a.matmul(&Matrix::from_vector(&v, true)?)?;
+
+                Ok(res.column(0))
+            },
+            (Operator::Mul, Value::Array(v), Value::Matrix(b)) => {
+                let res = Matrix::from_vector(&v, false)?.matmul(&b)?;
+
+                Ok(res.row(0))
+            },
+            (Operator::Add | Operator::Sub, lhs, rhs) => broadcast(lhs, op, rhs),
+            (Operator::Div, lhs, rhs) => Err(Error::runtime(format!(
+                "Cannot apply / to {} and {}; use ./ to divide entry by entry, or solve(A, b) to solve A*x = b",
+                lhs.kind(), rhs.kind()
+            )).with_code(ErrorCode::InvalidOperation)),
+            (Operator::Exp, lhs, rhs) => Err(not_a_matrix_power(&lhs, &rhs)),
+            (_, lhs, rhs) => Err(invalid_operation(lhs.kind(), &op.to_string(), rhs.kind())),
+        }
+    }
+
+    /// `M^n` for a square matrix and an integer `n`; a negative `n` powers the inverse.
+    fn matrix_power(mat: Matrix, exp: &Value) -> Result<Value, Error> {
+        let n = match exp.to_rational() {
+            Some(n) if n.is_integer() => n.numer().to_i64(),
+            _ => None,
+        };
+
+        let n = match n {
+            Some(n) => n,
+            None => return Err(not_a_matrix_power(&Value::Matrix(mat), exp)),
+        };
+
+        let base = match n {
+            n if n < 0 => match linalg::inv(vec![Value::Matrix(mat)])? {
+                Value::Matrix(inverse) => inverse,
+                other => return Ok(other),
+            },
+            _ => mat,
+        };
+
+        base.pow(n.unsigned_abs()).map(Value::Matrix)
+    }
+}
+
+fn not_a_matrix_power(lhs: &Value, rhs: &Value) -> Error {
+    Error::runtime(format!(
+        "Cannot apply ^ to {} and {}; M^n needs a square matrix and an integer n, use .^ for an element-wise power",
+        lhs.kind(), rhs.kind()
+    )).with_code(ErrorCode::InvalidOperation)
+}
+
+pub(crate) fn division_by_zero() -> Error {
+    Error::runtime("Division by zero".to_string()).with_code(ErrorCode::InvalidOperation)
+}
+
+impl Value {
+    /// Arithmetic where an operand is complex, exact or neither; complex operands
+    /// take precedence, so `1/3 + 1i` is complex.
+    fn extended_op(op: &Operator, lhs: Value, rhs: Value) -> Result<Value, Error> {
+        if lhs.is_complex() || rhs.is_complex() {
+            Value::complex_op(op, lhs, rhs)
+        } else {
+            Value::exact_op(op, lhs, rhs)
+        }
+    }
+
+    fn complex_op(op: &Operator, lhs: Value, rhs: Value) -> Result<Value, Error> {
+        let (a, b) = match (lhs.as_complex(), rhs.as_complex()) {
+            (Some(a), Some(b)) => (a, b),
+            _ if matches!(lhs, Value::Array(_)) || matches!(rhs, Value::Array(_)) => return broadcast(lhs, op, rhs),
+            _ => return Err(invalid_operation(lhs.kind(), &op.to_string(), rhs.kind())),
+        };
+
+        match op {
+            Operator::Add => Ok(Value::Complex(a + b)),
+            Operator::Sub => Ok(Value::Complex(a - b)),
+            Operator::Mul | Operator::ElemMul => Ok(Value::Complex(a * b)),
+            Operator::Div | Operator::ElemDiv => Ok(Value::Complex(a / b)),
+            Operator::Exp | Operator::ElemExp => Ok(Value::Complex(a.pow(b))),
+            _ => lhs.compare(op, rhs),
+        }
+    }
+
+    /// Arithmetic where at least one operand is a `BigInt` or `Rational`. Integers and
+    /// fractions combine exactly, while a `Decimal` operand gives a `Decimal` result.
+    fn exact_op(op: &Operator, lhs: Value, rhs: Value) -> Result<Value, Error> {
+        let (a, b) = match (lhs.to_rational(), rhs.to_rational()) {
+            (Some(a), Some(b)) => (a, b),
+            _ => return match (lhs.as_f64(), rhs.as_f64()) {
+                (Some(a), Some(b)) => Value::Decimal(a).apply(op, Value::Decimal(b)),
+                _ if matches!(lhs, Value::Array(_)) || matches!(rhs, Value::Array(_)) => broadcast(lhs, op, rhs),
+                _ => Err(invalid_operation(lhs.kind(), &op.to_string(), rhs.kind())),
+            },
+        };
+
+        let res = match op {
+            Operator::Add => &a + &b,
+            Operator::Sub => &a - &b,
+            Operator::Mul | Operator::ElemMul => &a * &b,
+            Operator::Div | Operator::ElemDiv => a.checked_div(&b).ok_or_else(division_by_zero)?,
+            Operator::Exp | Operator::ElemExp => {
+                let exp = b.numer().to_i64().filter(|_| b.is_integer());
+
+                match exp {
+                    Some(exp) => a.pow(exp)?,
+                    // fractional (or huge) exponents leave the rationals
+                    None => return Ok(Value::Decimal(a.to_f64().powf(b.to_f64()))),
+                }
+            },
+            _ => return lhs.compare(op, rhs),
+        };
+
+        Ok(Value::from_rational(res))
+    }
+}
+
+fn map_array(arr: Vec<Value>, f: impl Fn(Value) -> Result<Value, Error>) -> Result<Value, Error> {
+    arr.into_iter()
+        .map(f)
+        .collect::<Result<Vec<Value>, Error>>()
+        .map(Value::Array)
+}
+
+fn format_shape(shape: &[usize]) -> String {
+    let dims: Vec<String> = shape.iter().map(|dim| dim.to_string()).collect();
+
+    format!("({})", dims.join(", "))
+}
+
+fn into_items(value: Value) -> Vec<Value> {
+    match value {
+        Value::Array(items) => items,
+        Value::Matrix(mat) => mat.to_rows(),
+        other => vec![other],
+    }
+}
+
+/// Turns a rectangular array of numbers back into a matrix.
+fn into_matrix(value: Value) -> Value {
+    match value {
+        Value::Array(items) => match Matrix::from_values(&items) {
+            Some(Ok(mat)) => Value::Matrix(mat),
+            _ => Value::Array(items),
+        },
+        other => other,
+    }
+}
+
+/// Applies `op` to two values using NumPy broadcasting rules: shapes are aligned
+/// on their trailing dimensions, and a dimension of length 1 (or a missing leading
+/// one) is repeated to match the other operand.
+fn broadcast(lhs: Value, op: &Operator, rhs: Value) -> Result<Value, Error> {
+    broadcast_with(lhs, &op.to_string(), rhs, &|x, y| x.apply(op, y))
+}
+
+/// Broadcasts two values like `broadcast`, combining scalar pairs with `f`. `label`
+/// names the operation in shape errors.
+pub(crate) fn broadcast_with(
+    lhs: Value,
+    label: &str,
+    rhs: Value,
+    f: &dyn Fn(Value, Value) -> Result<Value, Error>,
+) -> Result<Value, Error> {
+    let (ls, rs) = (lhs.shape(), rhs.shape());
+
+    if !ls.iter().rev().zip(rs.iter().rev()).all(|(a, b)| a == b || *a == 1 || *b == 1) {
+        return Err(Error::runtime(format!(
+            "Cannot broadcast shapes {} and {} for {}", format_shape(&ls), format_shape(&rs), label
+        )).with_code(ErrorCode::ShapeMismatch));
+    }
+
+    let matrix = lhs.is_matrix() || rhs.is_matrix();
+    let res = broadcast_items(lhs, ls.len(), label, rhs, rs.len(), f)?;
+
+    Ok(if matrix { into_matrix(res) } else { res })
+}
+
+fn broadcast_items(
+    lhs: Value,
+    ld: usize,
+    label: &str,
+    rhs: Value,
+    rd: usize,
+    f: &dyn Fn(Value, Value) -> Result<Value, Error>,
+) -> Result<Value, Error> {
+    if ld == 0 && rd == 0 {
+        return f(lhs, rhs);
+    }
+
+    if ld > rd {
+        return map_array(into_items(lhs), |x| broadcast_items(x, ld - 1, label, rhs.clone(), rd, f));
+    }
+
+    if rd > ld {
+        return map_array(into_items(rhs), |y| broadcast_items(lhs.clone(), ld, label, y, rd - 1, f));
+    }
+
+    let (lhs, rhs) = (into_items(lhs), into_items(rhs));
+
+    match (lhs.len(), rhs.len()) {
+        (n, m) if n == m => lhs.into_iter()
+            .zip(rhs)
+            .map(|(x, y)| broadcast_items(x, ld - 1, label, y, rd - 1, f))
+            .collect::<Result<Vec<Value>, Error>>()
+            .map(Value::Array),
+        (1, _) => map_array(rhs, |y| broadcast_items(lhs[0].clone(), ld - 1, label, y, rd - 1, f)),
+        (_, 1) => map_array(lhs, |x| broadcast_items(x, ld - 1, label, rhs[0].clone(), rd - 1, f)),
+        (n, m) => Err(Error::runtime(format!("Cannot apply {} to arrays of length {} and {}", label, n, m))
+            .with_code(ErrorCode::ShapeMismatch)),
+    }
+}
+
 impl ops::Add<i64> for Value {
-    type Output = Value;
+    type Output = Result<Value, Error>;
 
     fn add(self, rhs: i64) -> Self::Output {
         match self {
-            Value::Number(lhs) => Value::Number(lhs + rhs),
-            Value::Decimal(lhs) => Value::Decimal(lhs + (rhs as f64)),
-            Value::Array(lhs) => {
-                let arr = lhs.into_iter().map(|value| value + rhs).collect();
-
-                Value::Array(arr)
-            },
-            _ => panic!("{}", Interpreter::error("Invalid operation"))
+            Value::Number(lhs) => int_op(&Operator::Add, lhs, rhs),
+            Value::Decimal(lhs) => Ok(Value::Decimal(lhs + (rhs as f64))),
+            Value::Array(lhs) => map_array(lhs, |value| value + rhs),
+            other => Value::extended_op(&Operator::Add, other, Value::Number(rhs)),
         }
     }
 }
 
 impl ops::Add<f64> for Value {
-    type Output = Value;
+    type Output = Result<Value, Error>;
 
     fn add(self, rhs: f64) -> Self::Output {
         match self {
-            Value::Number(lhs) => Value::Decimal((lhs as f64) + rhs),
-            Value::Decimal(lhs) => Value::Decimal(lhs + rhs),
-            Value::Array(lhs) => {
-                let arr = lhs.into_iter().map(|value| value + rhs).collect();
-
-                Value::Array(arr)
-            },
-            _ => panic!("{}", Interpreter::error("Invalid operation"))
+            Value::Number(lhs) => Ok(Value::Decimal((lhs as f64) + rhs)),
+            Value::Decimal(lhs) => Ok(Value::Decimal(lhs + rhs)),
+            Value::Array(lhs) => map_array(lhs, |value| value + rhs),
+            other => Value::extended_op(&Operator::Add, other, Value::Decimal(rhs)),
         }
     }
 }
 
 impl ops::Add<Vec<Value>> for Value {
-    type Output = Value;
+    type Output = Result<Value, Error>;
 
     fn add(self, rhs: Vec<Value>) -> Self::Output {
         match self {
-            Value::Number(lhs) => {
-                let arr = rhs.into_iter().map(|value| value + lhs).collect();
-
-                Value::Array(arr)
-            },
-            Value::Decimal(lhs) => {
-                let arr = rhs.into_iter().map(|value| value + lhs).collect();
-
-                Value::Array(arr)
-            },
-            Value::Array(lhs) => {
-                let arr = rhs.into_iter().zip(lhs.into_iter()).map(|(x, y)| x + y).collect();
-
-                Value::Array(arr)
-            },
-            _ => panic!("{}", Interpreter::error("Invalid operation"))
+            Value::Number(lhs) => map_array(rhs, |value| value + lhs),
+            Value::Decimal(lhs) => map_array(rhs, |value| value + lhs),
+            Value::Array(lhs) => broadcast(Value::Array(lhs), &Operator::Add, Value::Array(rhs)),
+            other => Err(invalid_operation(other.kind(), "+", "Array")),
         }
     }
 }
 
 impl ops::Add<Value> for Value {
-    type Output = Value;
+    type Output = Result<Value, Error>;
 
-    fn add(self, rhs: Self) -> Self::Output {
-        match self {
-            Value::Number(lhs) => rhs + lhs,
-            Value::Decimal(lhs) => rhs + lhs,
-            Value::Array(lhs) => rhs + lhs,
-            _ => panic!("{}", Interpreter::error("Invalid operation"))
+    fn add(self, right: Self) -> Self::Output {
+        if let (Value::String(lhs), Value::String(rhs)) = (&self, &right) {
+            return Ok(Value::String(format!("{}{}", lhs, rhs)));
+        }
+
+        if self.is_matrix() || right.is_matrix() {
+            return Value::matrix_op(&Operator::Add, self, right);
+        }
+
+        if self.is_extended() || right.is_extended() {
+            return Value::extended_op(&Operator::Add, self, right);
+        }
+
+        match right {
+            Value::Number(rhs) => self + rhs,
+            Value::Decimal(rhs) => self + rhs,
+            Value::Array(rhs) => self + rhs,
+            other => Err(invalid_operation(self.kind(), "+", other.kind())),
         }
     }
 }
 
 impl ops::Neg for Value {
-    type Output = Value;
+    type Output = Result<Value, Error>;
 
     fn neg(self) -> Self::Output {
         match self {
-            Value::Number(lhs) => Value::Number(-lhs),
-            Value::Decimal(lhs) => Value::Decimal(-lhs),
-            Value::Array(lhs) => {
-                let arr = lhs.into_iter().map(|value| -value).collect();
-
-                Value::Array(arr)
-            },
-            _ => panic!("{}", Interpreter::error("Invalid operation"))
+            Value::Number(lhs) => checked_neg(lhs).map(Value::Number),
+            Value::Decimal(lhs) => Ok(Value::Decimal(-lhs)),
+            Value::BigInt(lhs) => Ok(Value::from_bigint(-lhs)),
+            Value::Rational(lhs) => Ok(Value::from_rational(-lhs)),
+            Value::Complex(lhs) => Ok(Value::Complex(-lhs)),
+            Value::Array(lhs) => map_array(lhs, |value| -value),
+            Value::Matrix(Matrix::Number(lhs)) => lhs.try_map(checked_neg).map(|m| Value::Matrix(Matrix::Number(m))),
+            Value::Matrix(lhs) => lhs.scalar_op(&Operator::Sub, &Value::Number(0), true).map(Value::Matrix),
+            other => Err(Error::runtime(format!("Cannot negate {}", other.kind()))
+                .with_code(ErrorCode::InvalidOperation)),
         }
     }
 }
 
 impl ops::Sub<i64> for Value {
-    type Output = Value;
+    type Output = Result<Value, Error>;
 
     fn sub(self, rhs: i64) -> Self::Output {
         match self {
-            Value::Number(lhs) => Value::Number(lhs - rhs),
-            Value::Decimal(lhs) => Value::Decimal(lhs - (rhs as f64)),
-            Value::Array(lhs) => {
-                let arr = lhs.into_iter().map(|value| value - rhs).collect();
-
-                Value::Array(arr)
-            },
-            _ => panic!("{}", Interpreter::error("Invalid operation"))
+            Value::Number(lhs) => int_op(&Operator::Sub, lhs, rhs),
+            Value::Decimal(lhs) => Ok(Value::Decimal(lhs - (rhs as f64))),
+            Value::Array(lhs) => map_array(lhs, |value| value - rhs),
+            other => Value::extended_op(&Operator::Sub, other, Value::Number(rhs)),
         }
     }
 }
 
 impl ops::Sub<f64> for Value {
-    type Output = Value;
+    type Output = Result<Value, Error>;
 
     fn sub(self, rhs: f64) -> Self::Output {
         match self {
-            Value::Number(lhs) => Value::Decimal((lhs as f64) - rhs),
-            Value::Decimal(lhs) => Value::Decimal(lhs - rhs),
-            Value::Array(lhs) => {
-                let arr = lhs.into_iter().map(|value| value - rhs).collect();
-
-                Value::Array(arr)
-            },
-            _ => panic!("{}", Interpreter::error("Invalid operation"))
+            Value::Number(lhs) => Ok(Value::Decimal((lhs as f64) - rhs)),
+            Value::Decimal(lhs) => Ok(Value::Decimal(lhs - rhs)),
+            Value::Array(lhs) => map_array(lhs, |value| value - rhs),
+            other => Value::extended_op(&Operator::Sub, other, Value::Decimal(rhs)),
         }
     }
 }
 
 impl ops::Sub<Vec<Value>> for Value {
-    type Output = Value;
+    type Output = Result<Value, Error>;
 
     fn sub(self, rhs: Vec<Value>) -> Self::Output {
         match self {
-            Value::Number(lhs) => {
-                let arr = rhs.into_iter().map(|value| value - lhs).collect();
-
-                Value::Array(arr)
-            },
-            Value::Decimal(lhs) => {
-                let arr = rhs.into_iter().map(|value| value - lhs).collect();
-
-                Value::Array(arr)
-            },
-            Value::Array(lhs) => {
-                let arr = rhs.into_iter().zip(lhs.into_iter()).map(|(x, y)| x - y).collect();
-
-                Value::Array(arr)
-            },
-            _ => panic!("{}", Interpreter::error("Invalid operation"))
+            Value::Number(lhs) => map_array(rhs, |value| Value::Number(lhs) - value),
+            Value::Decimal(lhs) => map_array(rhs, |value| Value::Decimal(lhs) - value),
+            Value::Array(lhs) => broadcast(Value::Array(lhs), &Operator::Sub, Value::Array(rhs)),
+            other => Err(invalid_operation(other.kind(), "-", "Array")),
         }
     }
 }
 
 impl ops::Sub<Value> for Value {
-    type Output = Value;
+    type Output = Result<Value, Error>;
 
     fn sub(self, right: Self) -> Self::Output {
+        if self.is_matrix() || right.is_matrix() {
+            return Value::matrix_op(&Operator::Sub, self, right);
+        }
+
+        if self.is_extended() || right.is_extended() {
+            return Value::extended_op(&Operator::Sub, self, right);
+        }
+
         match right {
             Value::Number(rhs) => self - rhs,
             Value::Decimal(rhs) => self - rhs,
             Value::Array(rhs) => self - rhs,
-            _ => panic!("{}", Interpreter::error("Invalid operation")),
+            other => Err(invalid_operation(self.kind(), "-", other.kind())),
         }
     }
 }
 
 impl ops::Mul<i64> for Value {
-    type Output = Value;
+    type Output = Result<Value, Error>;
 
     fn mul(self, rhs: i64) -> Self::Output {
         match self {
-            Value::Number(lhs) => Value::Number(lhs * rhs),
-            Value::Decimal(lhs) => Value::Decimal(lhs * (rhs as f64)),
-            Value::Array(lhs) => {
-                let arr = lhs.into_iter().map(|value| value * rhs).collect();
-
-                Value::Array(arr)
-            },
-            _ => panic!("{}", Interpreter::error("Invalid operation"))
+            Value::Number(lhs) => int_op(&Operator::Mul, lhs, rhs),
+            Value::Decimal(lhs) => Ok(Value::Decimal(lhs * (rhs as f64))),
+            Value::Array(lhs) => map_array(lhs, |value| value * rhs),
+            other => Value::extended_op(&Operator::Mul, other, Value::Number(rhs)),
         }
     }
 }
 
 impl ops::Mul<f64> for Value {
-    type Output = Value;
+    type Output = Result<Value, Error>;
 
     fn mul(self, rhs: f64) -> Self::Output {
         match self {
-            Value::Number(lhs) => Value::Decimal((lhs as f64) * rhs),
-            Value::Decimal(lhs) => Value::Decimal(lhs * rhs),
-            Value::Array(lhs) => {
-                let arr = lhs.into_iter().map(|value| value * rhs).collect();
-
-                Value::Array(arr)
-            },
-            _ => panic!("{}", Interpreter::error("Invalid operation"))
+            Value::Number(lhs) => Ok(Value::Decimal((lhs as f64) * rhs)),
+            Value::Decimal(lhs) => Ok(Value::Decimal(lhs * rhs)),
+            Value::Array(lhs) => map_array(lhs, |value| value * rhs),
+            other => Value::extended_op(&Operator::Mul, other, Value::Decimal(rhs)),
         }
     }
 }
 
 impl ops::Mul<Vec<Value>> for Value {
-    type Output = Value;
+    type Output = Result<Value, Error>;
 
     fn mul(self, lhs: Vec<Value>) -> Self::Output {
         match self {
-            Value::Number(rhs) => {
-                let arr = lhs.into_iter().map(|value| value * rhs).collect();
-
-                Value::Array(arr)
-            }
-            Value::Decimal(rhs) => {
-                let arr = lhs.into_iter().map(|value| value * rhs).collect();
-
-                Value::Array(arr)
+            Value::Number(rhs) => map_array(lhs, |value| value * rhs),
+            Value::Decimal(rhs) => map_array(lhs, |value| value * rhs),
+            Value::Array(rhs) => match (Matrix::from_values(&lhs), Matrix::from_values(&rhs)) {
+                (Some(a), Some(b)) => a?.matmul(&b?).map(Value::Matrix),
+                _ => Err(invalid_operation("Array", "*", "Array")),
             },
-            Value::Array(rhs) => {
-                let r1 = lhs.len();
-                let r2 = rhs.len();
-
-                if r1 == 0 || r2 == 0 {
-                    panic!("{}", Interpreter::error("Cannot multiply an empty matrix"))
-                } else {
-                    let c1 = lhs.iter().next().unwrap().as_array().len();
-                    let c2 = rhs.iter().next().unwrap().as_array().len();
-
-                    if c1 != r2 {
-                        panic!("{}", Interpreter::error("Cannot multiply matrices"));
-                    }
-
-                    // lhs_iter = lhs.into_iter();
-                    // rhs_iter = rhs.into_iter();
-
-                    let mut res: Vec<Value> = Vec::with_capacity(r1);
-
-                    for _ in 0..r1 {
-                        let mut vec = Vec::with_capacity(c2);
-
-                        for _ in 0..c2 {
-                            vec.push(Value::Number(0));
-                        }
-
-                        res.push(Value::Array(vec));
-                    }
-
-                    for i in 0..r1 {
-                        let res_vec = res[i].as_array_mut();
-
-                        for j in 0..c2 {
-                            for k in 0..c1 {
-                                let lhs_vec = lhs[i].as_array();
-                                let rhs_vec = rhs[k].as_array();
-
-
-                                res_vec[j] = res_vec[j].clone() + lhs_vec[k].clone() * rhs_vec[j].clone();
-                            }
-                        }
-                    }
-
-                    return Value::Array(res);
-                }
-            },
-            _ => panic!("{}", Interpreter::error("Invalid operation"))
+            other => Err(invalid_operation("Array", "*", other.kind())),
         }
     }
 }
 
 impl ops::Mul<Value> for Value {
-    type Output = Value;
+    type Output = Result<Value, Error>;
 
     fn mul(self, rhs: Self) -> Self::Output {
+        if self.is_matrix() || rhs.is_matrix() {
+            return Value::matrix_op(&Operator::Mul, self, rhs);
+        }
+
+        if self.is_extended() || rhs.is_extended() {
+            return Value::extended_op(&Operator::Mul, self, rhs);
+        }
+
         match self {
             Value::Number(lhs) => rhs * lhs,
             Value::Decimal(lhs) => rhs * lhs,
             Value::Array(lhs) => rhs * lhs,
-            _ => panic!("{}", Interpreter::error("Invalid operation"))
+            other => Err(invalid_operation(other.kind(), "*", rhs.kind())),
         }
     }
 }
 
 impl ops::Div<i64> for Value {
-    type Output = Value;
+    type Output = Result<Value, Error>;
 
     fn div(self, rhs: i64) -> Self::Output {
         match self {
-            Value::Number(lhs) => Value::Decimal((lhs as f64) / (rhs as f64)),
-            Value::Decimal(lhs) => Value::Decimal(lhs / (rhs as f64)),
-            Value::Array(lhs) => {
-                let arr = lhs.into_iter().map(|value| value / rhs).collect();
-
-                Value::Array(arr)
-            },
-            _ => panic!("{}", Interpreter::error("Invalid operation"))
+            Value::Number(lhs) => Ok(Value::Decimal((lhs as f64) / (rhs as f64))),
+            Value::Decimal(lhs) => Ok(Value::Decimal(lhs / (rhs as f64))),
+            Value::Array(lhs) => map_array(lhs, |value| value / rhs),
+            other => Value::extended_op(&Operator::Div, other, Value::Number(rhs)),
         }
     }
 }
 
 impl ops::Div<f64> for Value {
-    type Output = Value;
+    type Output = Result<Value, Error>;
 
     fn div(self, rhs: f64) -> Self::Output {
         match self {
-            Value::Number(lhs) => Value::Decimal((lhs as f64) / rhs),
-            Value::Decimal(lhs) => Value::Decimal(lhs / rhs),
-            Value::Array(lhs) => {
-                let arr = lhs.into_iter().map(|value| value / rhs).collect();
-
-                Value::Array(arr)
-            },
-            _ => panic!("{}", Interpreter::error("Invalid operation"))
+            Value::Number(lhs) => Ok(Value::Decimal((lhs as f64) / rhs)),
+            Value::Decimal(lhs) => Ok(Value::Decimal(lhs / rhs)),
+            Value::Array(lhs) => map_array(lhs, |value| value / rhs),
+            other => Value::extended_op(&Operator::Div, other, Value::Decimal(rhs)),
         }
     }
 }
 
 impl ops::Div<Value> for Value {
-    type Output = Value;
+    type Output = Result<Value, Error>;
 
     fn div(self, right: Self) -> Self::Output {
+        if self.is_matrix() || right.is_matrix() {
+            return Value::matrix_op(&Operator::Div, self, right);
+        }
+
+        if self.is_extended() || right.is_extended() {
+            return Value::extended_op(&Operator::Div, self, right);
+        }
+
         match right {
             Value::Number(rhs) => self / rhs,
             Value::Decimal(rhs) => self / rhs,
-            Value::Array(_) => panic!("Impossible operation"),
-            _ => panic!("{}", Interpreter::error("Invalid operation"))
+            Value::Array(rhs) => broadcast(self, &Operator::Div, Value::Array(rhs)),
+            other => Err(invalid_operation(self.kind(), "/", other.kind())),
         }
     }
 }
 
 impl Value {
-    pub fn powi(self, rhs: i32) -> Value {
+    /// Raises to an integer power; a `Number` base with a non-negative exponent stays a `Number`.
+    pub fn powi(self, rhs: i32) -> Result<Value, Error> {
+        match self {
+            Value::Number(lhs) if rhs >= 0 => int_op(&Operator::Exp, lhs, rhs as i64),
+            Value::Number(lhs) => Ok(Value::Decimal((lhs as f64).powi(rhs))),
+            Value::Decimal(lhs) => Ok(Value::Decimal(lhs.powi(rhs))),
+            Value::Array(lhs) => map_array(lhs, |value| value.powi(rhs)),
+            other => Value::extended_op(&Operator::Exp, other, Value::Number(rhs as i64)),
+        }
+    }
+
+    pub fn powf(self, rhs: f64) -> Result<Value, Error> {
         match self {
-            Value::Number(lhs) => Value::Decimal((lhs as f64).powi(rhs)),
-            Value::Decimal(lhs) => Value::Decimal(lhs.powi(rhs)),
-            Value::Array(lhs) => {
-                let arr = lhs.into_iter().map(|value| value.powi(rhs)).collect();
+            Value::Number(lhs) => Ok(Value::Decimal((lhs as f64).powf(rhs))),
+            Value::Decimal(lhs) => Ok(Value::Decimal(lhs.powf(rhs))),
+            Value::Array(lhs) => map_array(lhs, |value| value.powf(rhs)),
+            other => Value::extended_op(&Operator::Exp, other, Value::Decimal(rhs)),
+        }
+    }
 
-                Value::Array(arr)
+    pub fn pow(self, right: Self) -> Result<Value, Error> {
+        if self.is_matrix() || right.is_matrix() {
+            return Value::matrix_op(&Operator::Exp, self, right);
+        }
+
+        if self.is_extended() || right.is_extended() {
+            return Value::extended_op(&Operator::Exp, self, right);
+        }
+
+        match right {
+            Value::Number(rhs) => match i32::try_from(rhs) {
+                Ok(rhs) => self.powi(rhs),
+                Err(_) => self.powf(rhs as f64),
             },
-            _ => panic!("{}", Interpreter::error("Invalid operation")),
+            Value::Decimal(rhs) => self.powf(rhs),
+            Value::Array(rhs) => broadcast(self, &Operator::Exp, Value::Array(rhs)),
+            other => Err(invalid_operation(self.kind(), "^", other.kind())),
         }
     }
+}
 
-    pub fn powf(self, rhs: f64) -> Value {
-        match self {
-            Value::Number(lhs) => Value::Decimal((lhs as f64).powf(rhs)),
-            Value::Decimal(lhs) => Value::Decimal(lhs.powf(rhs)),
-            Value::Array(lhs) => {
-                let arr = lhs.into_iter().map(|value| value.powf(rhs)).collect();
+impl Value {
+    /// Evaluates a binary arithmetic or comparison operator.
+    pub fn apply(self, op: &Operator, right: Self) -> Result<Value, Error> {
+        match op {
+            Operator::Add => self + right,
+            Operator::Sub => self - right,
+            Operator::Mul => self * right,
+            Operator::Div => self / right,
+            Operator::Exp => self.pow(right),
+            Operator::ElemMul | Operator::ElemDiv | Operator::ElemExp => self.elementwise(op, right),
+            _ => self.compare(op, right),
+        }
+    }
 
-                Value::Array(arr)
+    /// Evaluates `.*`, `./` or `.^`, which always work entry by entry, broadcasting
+    /// arrays and matrices of different shapes.
+    pub fn elementwise(self, op: &Operator, right: Self) -> Result<Value, Error> {
+        let base = match op {
+            Operator::ElemMul => Operator::Mul,
+            Operator::ElemDiv => Operator::Div,
+            _ => Operator::Exp,
+        };
+
+        match (self, right) {
+            (Value::Matrix(a), Value::Matrix(b)) if a.same_shape(&b) => a.elementwise(&base, &b).map(Value::Matrix),
+            (Value::Matrix(a), scalar) if scalar.is_scalar() => a.scalar_op(&base, &scalar, false).map(Value::Matrix),
+            (scalar, Value::Matrix(b)) if scalar.is_scalar() => b.scalar_op(&base, &scalar, true).map(Value::Matrix),
+            (lhs @ (Value::Matrix(_) | Value::Array(_)), rhs @ (Value::Matrix(_) | Value::Array(_))) => broadcast(lhs, op, rhs),
+            (Value::Array(lhs), rhs) => map_array(lhs, |value| value.elementwise(op, rhs.clone())),
+            (lhs, Value::Array(rhs)) => map_array(rhs, |value| lhs.clone().elementwise(op, value)),
+            (lhs, rhs) if lhs.is_scalar() && rhs.is_scalar() => match base {
+                Operator::Mul => lhs * rhs,
+                Operator::Div => lhs / rhs,
+                _ => lhs.pow(rhs),
             },
-            _ => panic!("{}", Interpreter::error("Invalid operation")),
+            (lhs, rhs) => Err(invalid_operation(lhs.kind(), &op.to_string(), rhs.kind())),
         }
     }
 
-    pub fn pow(self, right: Self) -> Value {
-        match right {
-            Value::Number(rhs) => self.powf(rhs as f64),
-            Value::Decimal(rhs) => self.powf(rhs),
-            _ => panic!("{}", Interpreter::error("Invalid operation"))
+    pub(crate) fn partial_cmp_scalar(&self, rhs: &Value) -> Option<Ordering> {
+        if self.is_exact() || rhs.is_exact() {
+            if let (Some(lhs), Some(rhs)) = (self.to_rational(), rhs.to_rational()) {
+                return Some(lhs.cmp(&rhs));
+            }
+        }
+
+        match (self, rhs) {
+            (Value::Number(lhs), Value::Number(rhs)) => lhs.partial_cmp(rhs),
+            (Value::Number(lhs), Value::Decimal(rhs)) => (*lhs as f64).partial_cmp(rhs),
+            (Value::Decimal(lhs), Value::Number(rhs)) => lhs.partial_cmp(&(*rhs as f64)),
+            (Value::Decimal(lhs), Value::Decimal(rhs)) => lhs.partial_cmp(rhs),
+            (Value::String(lhs), Value::String(rhs)) => lhs.partial_cmp(rhs),
+            (lhs, rhs) => lhs.as_f64()?.partial_cmp(&rhs.as_f64()?),
+        }
+    }
+
+    /// Evaluates a comparison operator. `==` and `!=` compare whole values, while
+    /// the ordering operators compare numbers and map element-wise over arrays.
+    pub fn compare(self, op: &Operator, right: Self) -> Result<Value, Error> {
+        match (op, self, right) {
+            (Operator::Equal, lhs, rhs) => Ok(Value::Bool(lhs == rhs)),
+            (Operator::NotEqual, lhs, rhs) => Ok(Value::Bool(lhs != rhs)),
+            (_, Value::Matrix(lhs), rhs) => Value::Array(lhs.to_rows()).compare(op, rhs),
+            (_, lhs, Value::Matrix(rhs)) => lhs.compare(op, Value::Array(rhs.to_rows())),
+            (_, Value::Array(lhs), Value::Array(rhs)) => broadcast(Value::Array(lhs), op, Value::Array(rhs)),
+            (_, Value::Array(lhs), rhs) => map_array(lhs, |value| value.compare(op, rhs.clone())),
+            (_, lhs, Value::Array(rhs)) => map_array(rhs, |value| lhs.clone().compare(op, value)),
+            (_, lhs, rhs) => {
+                let ordering = match lhs.partial_cmp_scalar(&rhs) {
+                    Some(ordering) => ordering,
+                    None => return Err(invalid_operation(lhs.kind(), &op.to_string(), rhs.kind())),
+                };
+
+                let res = match op {
+                    Operator::Less => ordering == Ordering::Less,
+                    Operator::Greater => ordering == Ordering::Greater,
+                    Operator::LessEqual => ordering != Ordering::Greater,
+                    Operator::GreaterEqual => ordering != Ordering::Less,
+                    _ => return Err(invalid_operation(lhs.kind(), &op.to_string(), rhs.kind())),
+                };
+
+                Ok(Value::Bool(res))
+            },
         }
     }
 }
 
-#[derive(Debug)]
+/// An evaluated index along one axis.
+#[derive(Debug, Clone, Copy)]
+pub enum Selector {
+    All,
+    At(i64),
+    Range(i64, i64),
+}
+
+fn out_of_bounds(message: String) -> Error {
+    Error::runtime(message).with_code(ErrorCode::IndexOutOfBounds)
+}
+
+impl Selector {
+    /// Resolves the selector against an axis of length `len` into a range of positions.
+    pub(crate) fn bounds(&self, len: usize) -> Result<std::ops::Range<usize>, Error> {
+        match *self {
+            Selector::All => Ok(0..len),
+            Selector::At(i) => {
+                if i < 0 || i as usize >= len {
+                    return Err(out_of_bounds(format!("Index {} out of bounds for length {}", i, len)));
+                }
+
+                Ok(i as usize..i as usize + 1)
+            },
+            Selector::Range(start, end) => {
+                if start < 0 || end < start || end as usize > len {
+                    return Err(out_of_bounds(format!("Slice {}..{} out of bounds for length {}", start, end, len)));
+                }
+
+                Ok(start as usize..end as usize)
+            },
+        }
+    }
+}
+
+impl Value {
+    fn indexable(&self) -> Result<&Vec<Value>, Error> {
+        match self {
+            Value::Array(arr) => Ok(arr),
+            other => Err(Error::runtime(format!("Cannot index into {}", other.kind()))
+                .with_code(ErrorCode::TypeMismatch)),
+        }
+    }
+
+    /// Selects elements, rows, columns or sub-arrays, one selector per nesting level.
+    pub fn index(&self, selectors: &[Selector]) -> Result<Value, Error> {
+        if let Value::Matrix(mat) = self {
+            return mat.index(selectors);
+        }
+
+        let (selector, rest) = match selectors.split_first() {
+            Some(split) => split,
+            None => return Ok(self.clone()),
+        };
+
+        let arr = self.indexable()?;
+        let range = selector.bounds(arr.len())?;
+
+        match selector {
+            Selector::At(_) => arr[range.start].index(rest),
+            _ => arr[range]
+                .iter()
+                .map(|item| item.index(rest))
+                .collect::<Result<Vec<Value>, Error>>()
+                .map(Value::Array),
+        }
+    }
+
+    /// Replaces the selected part of the value. A slice assigned an array takes one
+    /// item per slot, so the lengths must match; any other value is repeated.
+    pub fn assign_index(&mut self, selectors: &[Selector], value: Value) -> Result<(), Error> {
+        let (selector, rest) = match selectors.split_first() {
+            Some(split) => split,
+            None => {
+                *self = value;
+
+                return Ok(());
+            },
+        };
+
+        let kind = self.kind();
+        let arr = match self {
+            Value::Array(arr) => arr,
+            Value::Matrix(mat) => return mat.assign_index(selectors, value),
+            _ => return Err(Error::runtime(format!("Cannot index into {}", kind))
+                .with_code(ErrorCode::TypeMismatch)),
+        };
+
+        let range = selector.bounds(arr.len())?;
+
+        if let Selector::At(_) = selector {
+            return arr[range.start].assign_index(rest, value);
+        }
+
+        if let Value::Array(values) = &value {
+            if values.len() != range.len() {
+                return Err(Error::runtime(format!(
+                    "Cannot assign {} items to a slice of {}", values.len(), range.len()
+                )).with_code(ErrorCode::ShapeMismatch));
+            }
+        }
+
+        for (j, item) in arr[range].iter_mut().enumerate() {
+            let part = match &value {
+                Value::Array(values) => values[j].clone(),
+                other => other.clone(),
+            };
+
+            item.assign_index(rest, part)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RecordKind {
+    #[default]
+    Global,
+    Function,
+    /// The scope of an `if`, `while` or `for` body, which can see the record below it.
+    Block,
+}
+
+#[derive(Debug, Default)]
 pub struct ActivationRecord {
+    kind: RecordKind,
     members: HashMap<String, Value>
 }
 
 impl ActivationRecord {
     pub fn new() -> ActivationRecord {
+        ActivationRecord::with_kind(RecordKind::Global)
+    }
+
+    pub fn with_kind(kind: RecordKind) -> ActivationRecord {
         ActivationRecord {
+            kind,
             members: HashMap::new()
         }
     }
 
-    pub fn get(&self, key: &String) -> Option<&Value> {
-        return self.members.get(key)
+    pub fn kind(&self) -> RecordKind {
+        self.kind
+    }
+
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        self.members.get(key)
+    }
+
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut Value> {
+        self.members.get_mut(key)
     }
 
     pub fn insert(&mut self, key: String, value: Value) -> Option<Value> {
@@ -433,7 +1063,7 @@ impl ActivationRecord {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Default)]
 pub struct CallStack {
     records: Vec<ActivationRecord>
 }
@@ -469,7 +1099,242 @@ impl CallStack {
         self.records.pop()
     }
 
+    /// Finds the index of the record defining `name`, searching through Block records
+    /// down to the enclosing function (or global) record.
+    fn resolve_local(&self, name: &str) -> Option<usize> {
+        for (i, record) in self.records.iter().enumerate().rev() {
+            if record.get(name).is_some() {
+                return Some(i);
+            }
+
+            if record.kind() != RecordKind::Block {
+                break;
+            }
+        }
+
+        None
+    }
+
+    /// Like `resolve_local`, but falls back to the global record, so functions can
+    /// read globals and natives.
+    fn resolve(&self, name: &str) -> Option<usize> {
+        self.resolve_local(name).or_else(|| {
+            self.records.first()
+                .filter(|record| record.get(name).is_some())
+                .map(|_| 0)
+        })
+    }
+
+    pub fn lookup(&self, name: &str) -> Option<&Value> {
+        self.resolve(name).and_then(|i| self.records[i].get(name))
+    }
+
+    /// A mutable reference to `name`. Globals are out of reach from inside a
+    /// function, so a function body cannot modify them.
+    pub fn lookup_mut(&mut self, name: &str) -> Option<&mut Value> {
+        self.resolve_local(name).and_then(|i| self.records[i].get_mut(name))
+    }
+
+    /// Updates `name` in the record that defines it, or defines it in the current
+    /// record. Inside a function only its own scopes are searched, so assigning to a
+    /// global or native name there creates a local instead.
+    pub fn assign(&mut self, name: String, value: Value) {
+        let index = self.resolve_local(&name).unwrap_or(self.records.len() - 1);
+
+        self.records[index].insert(name, value);
+    }
+
+    /// The number of function calls on the stack.
+    pub fn depth(&self) -> usize {
+        self.records.iter().filter(|record| record.kind() == RecordKind::Function).count()
+    }
+
     pub fn len(&self) -> usize {
         self.records.len()
     }
-}
\ No newline at end of file
+
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::matrix::Dense;
+
+    fn global(name: &str, value: Value) -> CallStack {
+        let mut ar = ActivationRecord::new();
+
+        ar.insert(String::from(name), value);
+
+        CallStack::from_record(ar)
+    }
+
+    #[test]
+    fn assignment_in_function_binds_locally() {
+        let mut stack = global("x", Value::Number(1));
+
+        stack.push(ActivationRecord::with_kind(RecordKind::Function));
+        stack.assign(String::from("x"), Value::Number(2));
+
+        assert_eq!(stack.lookup("x"), Some(&Value::Number(2)));
+
+        stack.pop();
+
+        assert_eq!(stack.lookup("x"), Some(&Value::Number(1)));
+    }
+
+    #[test]
+    fn assignment_in_block_updates_enclosing_scope() {
+        let mut stack = global("x", Value::Number(1));
+
+        stack.push(ActivationRecord::with_kind(RecordKind::Block));
+        stack.assign(String::from("x"), Value::Number(2));
+        stack.pop();
+
+        assert_eq!(stack.lookup("x"), Some(&Value::Number(2)));
+    }
+
+    #[test]
+    fn function_cannot_mutate_globals_in_place() {
+        let mut stack = global("a", Value::Array(vec![Value::Number(1)]));
+
+        stack.push(ActivationRecord::with_kind(RecordKind::Function));
+
+        assert!(stack.lookup("a").is_some());
+        assert!(stack.lookup_mut("a").is_none());
+    }
+
+    fn numbers(items: &[i64]) -> Value {
+        Value::Array(items.iter().map(|&x| Value::Number(x)).collect())
+    }
+
+    fn matrix(rows: usize, cols: usize, data: &[i64]) -> Value {
+        Value::Matrix(Matrix::Number(Dense::new(rows, cols, data.to_vec()).unwrap()))
+    }
+
+    #[test]
+    fn index_and_slice_arrays() {
+        let a = numbers(&[10, 20, 30, 40]);
+
+        assert_eq!(a.index(&[Selector::At(2)]).unwrap(), Value::Number(30));
+        assert_eq!(a.index(&[Selector::Range(1, 3)]).unwrap(), numbers(&[20, 30]));
+        assert_eq!(a.index(&[Selector::Range(2, 2)]).unwrap(), numbers(&[]));
+        assert_eq!(a.index(&[Selector::At(4)]).unwrap_err().code(), ErrorCode::IndexOutOfBounds);
+        assert_eq!(a.index(&[Selector::Range(3, 1)]).unwrap_err().code(), ErrorCode::IndexOutOfBounds);
+        assert_eq!(Value::Number(1).index(&[Selector::At(0)]).unwrap_err().code(), ErrorCode::TypeMismatch);
+
+        let nested = Value::Array(vec![numbers(&[1, 2]), numbers(&[3, 4])]);
+        assert_eq!(nested.index(&[Selector::At(1), Selector::At(0)]).unwrap(), Value::Number(3));
+    }
+
+    #[test]
+    fn indexed_assignment_distributes_or_repeats() {
+        let mut a = numbers(&[1, 2, 3]);
+
+        a.assign_index(&[Selector::At(1)], Value::Number(9)).unwrap();
+        assert_eq!(a, numbers(&[1, 9, 3]));
+
+        a.assign_index(&[Selector::Range(0, 2)], numbers(&[7, 8])).unwrap();
+        assert_eq!(a, numbers(&[7, 8, 3]));
+
+        a.assign_index(&[Selector::Range(1, 3)], Value::Number(0)).unwrap();
+        assert_eq!(a, numbers(&[7, 0, 0]));
+    }
+
+    #[test]
+    fn slice_assignment_needs_matching_lengths() {
+        let mut a = numbers(&[1, 2, 3]);
+
+        let err = a.assign_index(&[Selector::Range(0, 2)], numbers(&[7, 8, 9])).unwrap_err();
+        assert_eq!(err.code(), ErrorCode::ShapeMismatch);
+        assert_eq!(a, numbers(&[1, 2, 3]));
+
+        let err = a.assign_index(&[Selector::At(5)], Value::Number(0)).unwrap_err();
+        assert_eq!(err.code(), ErrorCode::IndexOutOfBounds);
+    }
+
+    #[test]
+    fn strings_concatenate_with_plus() {
+        let text = |value: &str| Value::String(String::from(value));
+
+        assert_eq!((text("ab") + text("cd")).unwrap(), text("abcd"));
+        assert_eq!((text("a") + Value::Number(1)).unwrap_err().code(), ErrorCode::InvalidOperation);
+    }
+
+    #[test]
+    fn elementwise_operators_differ_from_matrix_ones() {
+        let a = matrix(2, 2, &[1, 2, 3, 4]);
+        let b = matrix(2, 2, &[5, 6, 7, 8]);
+
+        assert_eq!(a.clone().apply(&Operator::Mul, b.clone()).unwrap(), matrix(2, 2, &[19, 22, 43, 50]));
+        assert_eq!(a.elementwise(&Operator::ElemMul, b).unwrap(), matrix(2, 2, &[5, 12, 21, 32]));
+        assert_eq!(
+            numbers(&[1, 2, 3]).elementwise(&Operator::ElemDiv, numbers(&[2, 4, 8])).unwrap(),
+            Value::Array(vec![Value::Decimal(0.5), Value::Decimal(0.5), Value::Decimal(0.375)])
+        );
+        assert_eq!(numbers(&[1, 2, 3]).elementwise(&Operator::ElemExp, Value::Number(2)).unwrap(), numbers(&[1, 4, 9]));
+
+        let err = numbers(&[1, 2, 3]).apply(&Operator::Mul, numbers(&[4, 5, 6])).unwrap_err();
+        assert_eq!(err.code(), ErrorCode::InvalidOperation);
+
+        let err = numbers(&[1, 2]).apply(&Operator::Add, numbers(&[1, 2, 3])).unwrap_err();
+        assert_eq!(err.code(), ErrorCode::ShapeMismatch);
+    }
+
+    #[test]
+    fn broadcasting_stretches_rows_and_columns() {
+        let m = matrix(2, 3, &[1, 2, 3, 4, 5, 6]);
+
+        assert_eq!(m.clone().apply(&Operator::Add, numbers(&[10, 20, 30])).unwrap(), matrix(2, 3, &[11, 22, 33, 14, 25, 36]));
+        assert_eq!(
+            m.apply(&Operator::Sub, matrix(2, 1, &[1, 4])).unwrap(),
+            matrix(2, 3, &[0, 1, 2, 0, 1, 2])
+        );
+        assert_eq!(
+            numbers(&[1, 2]).elementwise(&Operator::ElemMul, numbers(&[3, 4])).unwrap(),
+            numbers(&[3, 8])
+        );
+    }
+
+    #[test]
+    fn broadcasting_rejects_mismatched_shapes() {
+        let m = matrix(2, 3, &[1, 2, 3, 4, 5, 6]);
+
+        let err = m.clone().apply(&Operator::Add, numbers(&[1, 2])).unwrap_err();
+        assert_eq!(err.code(), ErrorCode::ShapeMismatch);
+
+        let err = m.apply(&Operator::Sub, matrix(3, 2, &[1, 2, 3, 4, 5, 6])).unwrap_err();
+        assert_eq!(err.code(), ErrorCode::ShapeMismatch);
+
+        let err = numbers(&[1, 2, 3]).elementwise(&Operator::ElemMul, numbers(&[1, 2])).unwrap_err();
+        assert_eq!(err.code(), ErrorCode::ShapeMismatch);
+    }
+
+    #[test]
+    fn matrix_power_multiplies_instead_of_broadcasting() {
+        let m = matrix(2, 2, &[1, 2, 3, 4]);
+
+        assert_eq!(m.clone().pow(Value::Number(2)).unwrap(), matrix(2, 2, &[7, 10, 15, 22]));
+        assert_eq!(m.clone().pow(Value::Number(0)).unwrap(), matrix(2, 2, &[1, 0, 0, 1]));
+        assert_eq!(m.clone().elementwise(&Operator::ElemExp, Value::Number(2)).unwrap(), matrix(2, 2, &[1, 4, 9, 16]));
+
+        let err = m.pow(Value::Decimal(0.5)).unwrap_err();
+        assert_eq!(err.code(), ErrorCode::InvalidOperation);
+
+        let err = matrix(2, 3, &[1, 2, 3, 4, 5, 6]).pow(Value::Number(2)).unwrap_err();
+        assert_eq!(err.code(), ErrorCode::ShapeMismatch);
+    }
+
+    #[test]
+    fn matrix_division_needs_a_scalar_divisor() {
+        let m = matrix(2, 2, &[2, 4, 6, 8]);
+
+        assert_eq!(m.clone().apply(&Operator::Div, Value::Number(2)).unwrap(), matrix(2, 2, &[1, 2, 3, 4]));
+        assert_eq!(m.clone().elementwise(&Operator::ElemDiv, m.clone()).unwrap(), matrix(2, 2, &[1, 1, 1, 1]));
+
+        let err = m.clone().apply(&Operator::Div, m).unwrap_err();
+        assert_eq!(err.code(), ErrorCode::InvalidOperation);
+    }
+}
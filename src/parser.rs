@@ -1,365 +1,865 @@
 use std::iter::{Iterator, Peekable};
+use std::mem;
 use crate::lexer::{Lexer, Token, TokenKind};
-use crate::ast::{Block, Node, Operator};
-use crate::error::Error;
+use crate::ast::{Block, Index, Node, NodeKind, Operator};
+use crate::bigint::BigInt;
+use crate::error::{Error, ErrorCode};
+use crate::span::Span;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Assoc {
+    Left,
+    Right,
+}
+
+/// Binding power and associativity of every binary operator. Higher binds tighter;
+/// tokens that are not listed end the expression.
+const BINARY_OPERATORS: &[(TokenKind, usize, Assoc)] = &[
+    (TokenKind::Less, 5, Assoc::Left),
+    (TokenKind::Greater, 5, Assoc::Left),
+    (TokenKind::LessEqual, 5, Assoc::Left),
+    (TokenKind::GreaterEqual, 5, Assoc::Left),
+    (TokenKind::Equal, 5, Assoc::Left),
+    (TokenKind::NotEqual, 5, Assoc::Left),
+    (TokenKind::Range, 7, Assoc::Left),
+    (TokenKind::Add, 10, Assoc::Left),
+    (TokenKind::Sub, 10, Assoc::Left),
+    (TokenKind::Mul, 20, Assoc::Left),
+    (TokenKind::Div, 20, Assoc::Left),
+    (TokenKind::ElemMul, 20, Assoc::Left),
+    (TokenKind::ElemDiv, 20, Assoc::Left),
+    (TokenKind::Exp, 30, Assoc::Right),
+    (TokenKind::ElemExp, 30, Assoc::Right),
+];
+
+/// Prefix `+` and `-` bind looser than `^`, so `-2^2` is `-(2^2)`.
+const UNARY_PRECEDENCE: usize = 25;
+
+fn binary_operator(kind: &TokenKind) -> Option<(usize, Assoc)> {
+    BINARY_OPERATORS
+        .iter()
+        .find(|(op, _, _)| op == kind)
+        .map(|(_, precedence, assoc)| (*precedence, *assoc))
+}
 
 pub struct Parser<'a> {
     source: Peekable<&'a mut Lexer<'a>>,
+    last: Span,
+    eof: Span,
+    /// Doc comment lines read since the last token.
+    docs: Vec<&'a str>,
+    /// Doc comment lines that preceded the last consumed token.
+    doc: Vec<&'a str>,
+    /// Whether syntax errors are collected in `errors` rather than returned.
+    recovering: bool,
+    errors: Vec<Error>,
 }
 
 impl<'a> Parser<'a> {
-    fn error(msg: &'a str) -> Error {
-        Error::ParserError(String::from(msg))
+    /// Creates an error located at the next token, or at the end of input. The
+    /// message names the token that was found; a malformed token reports why the
+    /// lexer rejected it instead.
+    fn error(&mut self, msg: &str) -> Error {
+        let (message, code) = match self.peek() {
+            Some(token) => match token.kind() {
+                TokenKind::Illegal(reason) => (format!("Invalid token '{}': {}", token.value(), reason), ErrorCode::InvalidSyntax),
+                TokenKind::Unknown => (format!("{}, found unknown token '{}'", msg, token.value()), ErrorCode::UnexpectedToken),
+                _ => (format!("{}, found '{}'", msg, token.value()), ErrorCode::UnexpectedToken),
+            },
+            None => (String::from(msg), ErrorCode::UnexpectedEnd),
+        };
+        let span = self.peek_span();
+
+        Error::parser(message).with_code(code).at(span)
     }
 
     fn eat(&mut self) -> Option<Token<'a>> {
-        self.source.next()
+        self.peek();
+
+        let token = self.source.next();
+
+        self.doc = mem::take(&mut self.docs);
+
+        if let Some(token) = &token {
+            self.last = token.span();
+        }
+
+        token
     }
 
     fn expect(&mut self, kind: TokenKind) -> Result<Token<'a>, Error> {
-        let err = Error::ParserError(format!("Expected {}", kind));
-        let token_opt = self.eat();
-
-        match token_opt {
-            Some(token) => {
-                if token.kind() != &kind {
-                    return Err(err);
-                }
+        match self.peek() {
+            Some(token) if token.kind() == &kind => Ok(self.eat().unwrap()),
+            _ => Err(self.error(&format!("Expected {}", kind))),
+        }
+    }
 
-                Ok(token)
+    fn expect_keyword(&mut self, keyword: &str) -> Result<Token<'a>, Error> {
+        match self.peek() {
+            Some(token) if token.kind() == &TokenKind::Identifier && token.value() == keyword => {
+                Ok(self.eat().unwrap())
             },
-            None => Err(err)
+            _ => Err(self.error(&format!("Expected {}", keyword))),
         }
     }
 
+    /// The next token, setting aside any doc comments in front of it.
     fn peek(&mut self) -> Option<&Token<'a>> {
+        while let Some(token) = self.source.next_if(|token| token.kind() == &TokenKind::DocComment) {
+            self.docs.push(token.value());
+        }
+
         self.source.peek()
     }
 
-    fn parse_number(&mut self) -> Node<'a> {
-        let value = self.eat().unwrap().value();
-        let res = Node::Number(value.parse::<i64>().unwrap());
+    fn peek_span(&mut self) -> Span {
+        let eof = self.eof;
+
+        self.peek().map_or(eof, |token| token.span())
+    }
+
+    /// Spans from `start` up to the end of the last consumed token.
+    fn span_from(&self, start: Span) -> Span {
+        start.to(self.last)
+    }
+
+    /// Parses an integer literal, which may have a `0x` or `0b` prefix and `_` separators.
+    fn parse_number(&mut self) -> Node {
+        let token = self.eat().unwrap();
+        let text = token.value().replace('_', "");
+
+        let (digits, radix) = match text.get(..2) {
+            Some("0x" | "0X") => (&text[2..], 16),
+            Some("0b" | "0B") => (&text[2..], 2),
+            _ => (text.as_str(), 10),
+        };
+
+        let kind = match i64::from_str_radix(digits, radix) {
+            Ok(value) => NodeKind::Number(value),
+            Err(_) => NodeKind::BigInt(BigInt::parse_radix(digits, radix).unwrap()),
+        };
+
+        Node::new(kind, token.span())
+    }
+
+    fn parse_decimal(&mut self) -> Node {
+        let token = self.eat().unwrap();
 
-        res
+        Node::new(NodeKind::Decimal(token.value().replace('_', "").parse::<f64>().unwrap()), token.span())
     }
 
-    fn parse_decimal(&mut self) -> Node<'a> {
-        let value = self.eat().unwrap().value();
-        let res = Node::Decimal(value.parse::<f64>().unwrap());
+    fn parse_imaginary(&mut self) -> Node {
+        let token = self.eat().unwrap();
 
-        res
+        Node::new(NodeKind::Imaginary(token.value().replace('_', "").parse::<f64>().unwrap()), token.span())
     }
 
-    fn parse_assignment(&mut self, name: &'a str) -> Result<Node<'a>, Error> {
+    /// Parses a string literal, replacing its escapes. The lexer has already
+    /// rejected unknown escapes.
+    fn parse_string(&mut self) -> Node {
+        let token = self.eat().unwrap();
+        let text = token.value();
+        let mut value = String::with_capacity(text.len());
+        let mut chars = text[1..text.len() - 1].chars();
+
+        while let Some(c) = chars.next() {
+            value.push(match c {
+                '\\' => match chars.next() {
+                    Some('n') => '\n',
+                    Some('t') => '\t',
+                    Some('r') => '\r',
+                    Some('0') => '\0',
+                    Some(other) => other,
+                    None => break,
+                },
+                c => c,
+            });
+        }
+
+        Node::new(NodeKind::String(value), token.span())
+    }
+
+    fn parse_assignment(&mut self, name: &'a str, indices: Vec<Index>, start: Span) -> Result<Node, Error> {
         self.eat();
 
         let right = self.parse_expr()?;
 
         match right {
             Some(rhs) => {
-                let res = Node::Assign {
-                    lhs: name,
+                let res = NodeKind::Assign {
+                    lhs: String::from(name),
+                    indices,
                     rhs: Box::new(rhs)
                 };
 
-                Ok(res)
+                Ok(Node::new(res, self.span_from(start)))
             },
-            None => Err(Parser::error("Expected right-hand-side of assignment operation")),
+            None => Err(self.error("Expected right-hand-side of assignment operation")),
         }
     }
 
-    fn parse_call(&mut self, name: &'a str) -> Result<Node<'a>, Error> {
-        self.eat();
-
-        let mut arguments = Vec::new();
+    fn parse_list<T>(
+        &mut self,
+        close: TokenKind,
+        item_name: &str,
+        parse_item: fn(&mut Self) -> Result<Option<T>, Error>,
+    ) -> Result<Vec<T>, Error> {
+        let mut items = Vec::new();
 
         loop {
-            let expr = self.parse_expr()?;
-
-            match expr {
-                Some(node) => arguments.push(node) ,
-                None => {
-                    self.expect(TokenKind::RightParen)?;
+            match self.peek().map(|token| token.kind()) {
+                Some(kind) if kind == &close => {
+                    self.eat();
                     break;
-                }
+                },
+                None => return Err(self.error(&format!("Expected {}", close))),
+                _ => {},
             }
-        }
 
-        Ok(Node::Call {
-            function: name,
-            arguments,
-        })
-    }
+            match parse_item(self)? {
+                Some(item) => items.push(item),
+                None => return Err(self.error(&format!("Expected {}", item_name))),
+            }
 
-    fn parse_block(&mut self, expect_braces: bool) -> Result<Block<'a>, Error> {
-        if expect_braces {
-            self.expect(TokenKind::BlockStart)?;
+            match self.peek().map(|token| token.kind()) {
+                Some(TokenKind::Separator) => {
+                    self.eat();
+                },
+                Some(kind) if kind == &close => {},
+                Some(_) => return Err(self.error(&format!("Expected Separator or {}", close))),
+                None => return Err(self.error(&format!("Expected {}", close))),
+            }
         }
 
-        let mut nodes: Vec<Node<'a>> = Vec::new();
+        Ok(items)
+    }
 
-        loop {
-            let expr = self.parse_expr()?;
+    fn parse_index(&mut self) -> Result<Option<Index>, Error> {
+        match self.peek().map(|token| token.kind()) {
+            Some(TokenKind::Colon) => {
+                self.eat();
 
-            if !expr.is_none() {
-                nodes.push(expr.unwrap());
-            }
+                Ok(Some(Index::All))
+            },
+            _ => Ok(self.parse_expr()?.map(Index::Expr)),
+        }
+    }
 
-            let token = self.peek();
+    fn parse_indices(&mut self) -> Result<Vec<Index>, Error> {
+        self.eat();
 
-            if token.is_none() || token.unwrap().kind() != &TokenKind::Semicolon {
-                break;
-            }
-        }
+        self.parse_list(TokenKind::ArrayEnd, "index", Parser::parse_index)
+    }
 
-        if expect_braces {
-            self.expect(TokenKind::BlockEnd)?;
+    /// Parses any `[...]` index expressions following `target`.
+    fn parse_postfix(&mut self, mut target: Node) -> Result<Node, Error> {
+        while let Some(TokenKind::ArrayStart) = self.peek().map(|token| token.kind()) {
+            let start = target.span;
+            let indices = self.parse_indices()?;
+
+            target = Node::new(NodeKind::Index {
+                target: Box::new(target),
+                indices,
+            }, self.span_from(start));
         }
 
-        Ok(Block(nodes))
+        Ok(target)
     }
 
-    fn parse_function_parameter(&mut self) -> Result<Option<Node<'a>>, Error> {
-        let name_token_opt = self.peek();
+    fn parse_call(&mut self, name: &'a str, start: Span) -> Result<Node, Error> {
+        self.eat();
+
+        let arguments = self.parse_list(TokenKind::RightParen, "argument", Parser::parse_expr)?;
+        let res = NodeKind::Call {
+            function: String::from(name),
+            arguments,
+        };
 
-        if name_token_opt.is_none() {
-            return Ok(None)
+        Ok(Node::new(res, self.span_from(start)))
+    }
+
+    fn parse_block(&mut self, expect_braces: bool) -> Result<Block, Error> {
+        if expect_braces {
+            self.expect(TokenKind::BlockStart)?;
         }
 
-        let name_token = name_token_opt.unwrap();
+        let nodes = self.parse_statements(expect_braces)?;
 
-        if name_token.kind() != &TokenKind::Identifier {
-            return Ok(None)
+        if expect_braces {
+            self.expect(TokenKind::BlockEnd)?;
         }
 
-        let name = name_token.value();
+        Ok(Block(nodes))
+    }
+
+    fn parse_function_parameter(&mut self) -> Result<Option<Node>, Error> {
+        let (name, start) = match self.peek() {
+            Some(token) if token.kind() == &TokenKind::Identifier => (token.value(), token.span()),
+            _ => return Ok(None),
+        };
 
         self.eat();
         self.expect(TokenKind::Colon)?;
 
         let kind = self.expect(TokenKind::Identifier)?.value();
+        let res = NodeKind::Declaration {
+            name: String::from(name),
+            kind: String::from(kind),
+        };
 
-        Ok(Some(Node::Declaration {
-            name,
-            kind
-        }))
+        Ok(Some(Node::new(res, self.span_from(start))))
     }
 
-    fn parse_function(&mut self) -> Result<Node<'a>, Error> {
+    fn parse_function(&mut self, start: Span) -> Result<Node, Error> {
+        let doc = mem::take(&mut self.doc);
         let name = self.expect(TokenKind::Identifier)?.value();
-        let mut params: Vec<Node<'a>> = Vec::new();
 
         self.expect(TokenKind::LeftParen)?;
 
-        loop {
-            let param = self.parse_function_parameter()?;
-
-            if param.is_none() {
-                break;
-            } else {
-                params.push(param.unwrap());
-            }
-        }
-
-        self.expect(TokenKind::RightParen)?;
+        let params = self.parse_list(TokenKind::RightParen, "parameter", Parser::parse_function_parameter)?;
 
         let nodes = self.parse_block(true)?;
 
-        Ok(Node::Function {
-            name,
+        let res = NodeKind::Function {
+            name: String::from(name),
             parameters: params,
-            body: nodes
-        })
+            body: nodes,
+            doc: if doc.is_empty() { None } else { Some(doc.join("\n")) },
+        };
+
+        Ok(Node::new(res, self.span_from(start)))
     }
 
-    fn parse_identifier(&mut self) -> Result<Node<'a>, Error> {
-        let name = self.eat().unwrap().value();
+    fn parse_if(&mut self, start: Span) -> Result<Node, Error> {
+        let condition = match self.parse_expr()? {
+            Some(expr) => expr,
+            None => return Err(self.error("Expected condition after if")),
+        };
+
+        let then = self.parse_block(true)?;
+
+        let otherwise = match self.peek() {
+            Some(token) if token.kind() == &TokenKind::Identifier && token.value() == "else" => {
+                self.eat();
+
+                match self.peek() {
+                    Some(token) if token.kind() == &TokenKind::Identifier && token.value() == "if" => {
+                        let start = self.eat().unwrap().span();
+
+                        Some(Block(vec![self.parse_if(start)?]))
+                    },
+                    _ => Some(self.parse_block(true)?),
+                }
+            },
+            _ => None,
+        };
+
+        let res = NodeKind::If {
+            condition: Box::new(condition),
+            then,
+            otherwise,
+        };
+
+        Ok(Node::new(res, self.span_from(start)))
+    }
+
+    fn parse_while(&mut self, start: Span) -> Result<Node, Error> {
+        let condition = match self.parse_expr()? {
+            Some(expr) => expr,
+            None => return Err(self.error("Expected condition after while")),
+        };
+
+        let body = self.parse_block(true)?;
+        let res = NodeKind::While {
+            condition: Box::new(condition),
+            body,
+        };
+
+        Ok(Node::new(res, self.span_from(start)))
+    }
+
+    fn parse_for(&mut self, start: Span) -> Result<Node, Error> {
+        let variable = self.expect(TokenKind::Identifier)?.value();
+
+        self.expect_keyword("in")?;
+
+        let iterable = match self.parse_expr()? {
+            Some(expr) => expr,
+            None => return Err(self.error("Expected an array or range to iterate over")),
+        };
+
+        let body = self.parse_block(true)?;
+        let res = NodeKind::For {
+            variable: String::from(variable),
+            iterable: Box::new(iterable),
+            body,
+        };
+
+        Ok(Node::new(res, self.span_from(start)))
+    }
+
+    fn parse_identifier(&mut self) -> Result<Node, Error> {
+        let token = self.eat().unwrap();
+        let (name, start) = (token.value(), token.span());
 
         match name {
-            "fn" => self.parse_function(),
+            "fn" => self.parse_function(start),
+            "if" => self.parse_if(start),
+            "while" => self.parse_while(start),
+            "for" => self.parse_for(start),
+            "true" => Ok(Node::new(NodeKind::Bool(true), start)),
+            "false" => Ok(Node::new(NodeKind::Bool(false), start)),
             _ => {
-                let token = self.peek();
+                match self.peek().map(|token| token.kind()) {
+                    Some(TokenKind::Assign) => self.parse_assignment(name, Vec::new(), start),
+                    Some(TokenKind::LeftParen) => self.parse_call(name, start),
+                    Some(TokenKind::ArrayStart) => {
+                        let indices = self.parse_indices()?;
+
+                        if let Some(TokenKind::Assign) = self.peek().map(|token| token.kind()) {
+                            return self.parse_assignment(name, indices, start);
+                        }
 
-                if token.is_none() {
-                    Ok(Node::Variable(name))
-                } else {
-                    match token.unwrap().kind() {
-                        &TokenKind::Assign => {
-                            self.parse_assignment(name)
-                        },
-                        &TokenKind::LeftParen => {
-                            self.parse_call(name)
-                        },
-                        _ => Ok(Node::Variable(name)),
-                    }
+                        let res = NodeKind::Index {
+                            target: Box::new(Node::new(NodeKind::Variable(String::from(name)), start)),
+                            indices,
+                        };
+
+                        Ok(Node::new(res, self.span_from(start)))
+                    },
+                    _ => Ok(Node::new(NodeKind::Variable(String::from(name)), start)),
                 }
             }
         }
     }
 
-    fn parse_paren_expr(&mut self) -> Result<Node<'a>, Error> {
-        self.eat();
+    fn parse_paren_expr(&mut self) -> Result<Node, Error> {
+        let start = self.eat().unwrap().span();
 
         let val = self.parse_expr()?;
 
         match val {
-            Some(expr) => {
+            Some(mut expr) => {
                 self.expect(TokenKind::RightParen)?;
 
+                expr.span = self.span_from(start);
+
                 Ok(expr)
             },
-            None => Err(Parser::error("Expected expression inside parenthesis"))
+            None => Err(self.error("Expected expression inside parenthesis"))
         }
     }
 
-    fn parse_array_expr(&mut self) -> Result<Node<'a>, Error> {
-        self.eat();
+    fn parse_array_expr(&mut self) -> Result<Node, Error> {
+        let start = self.eat().unwrap().span();
+        let nodes = self.parse_list(TokenKind::ArrayEnd, "array element", Parser::parse_expr)?;
 
-        let mut token;
-        let mut nodes = Vec::new();
+        Ok(Node::new(NodeKind::Array(nodes), self.span_from(start)))
+    }
 
-        loop {
-            let node = self.parse_expr()?;
+    fn parse_unary_op(&mut self) -> Result<Node, Error> {
+        let token = self.eat().unwrap();
+        let start = token.span();
+        let op: Operator = token.value().into();
 
-            if node.is_none() {
-                break;
-            } else {
-                nodes.push(node.unwrap());
-            }
+        let value = self.parse_primary_expr()?;
 
-            token = self.peek();
+        match value {
+            Some(expr) => {
+                let res = NodeKind::UnaryOp {
+                    op,
+                    rhs: Box::new(self.parse_expr_right(UNARY_PRECEDENCE, expr)?)
+                };
 
-            if !token.is_none() {
-                if token.unwrap().kind() != &TokenKind::Separator {
-                    // expected separator
-                    break;
-                } else {
-                    self.eat();
-                }
-            }
+                Ok(Node::new(res, self.span_from(start)))
+            },
+            None => Err(self.error("Expected a right-hand-side node"))
         }
+    }
+
+    /// `√x` is `sqrt(x)`, binding like a prefix minus so `√x^2` is `sqrt(x^2)`.
+    fn parse_sqrt(&mut self) -> Result<Node, Error> {
+        let start = self.eat().unwrap().span();
 
-        self.expect(TokenKind::ArrayEnd)?;
+        let value = match self.parse_primary_expr()? {
+            Some(expr) => expr,
+            None => return Err(self.error("Expected a right-hand-side node")),
+        };
 
-        Ok(Node::Array(nodes))
+        let res = NodeKind::Call {
+            function: String::from("sqrt"),
+            arguments: vec![self.parse_expr_right(UNARY_PRECEDENCE, value)?],
+        };
+
+        Ok(Node::new(res, self.span_from(start)))
     }
 
-    fn parse_unary_op(&mut self) -> Result<Node<'a>, Error> {
-        let op: Operator = self.eat().unwrap().value().into();
+    fn parse_primary_expr(&mut self) -> Result<Option<Node>, Error> {
+        let kind = match self.peek() {
+            Some(token) => token.kind(),
+            None => return Ok(None),
+        };
+
+        let node = match kind {
+            TokenKind::Add | TokenKind::Sub => return self.parse_unary_op().map(Some),
+            TokenKind::Sqrt => return self.parse_sqrt().map(Some),
+            TokenKind::Number => self.parse_number(),
+            TokenKind::Decimal => self.parse_decimal(),
+            TokenKind::Imaginary => self.parse_imaginary(),
+            TokenKind::String => self.parse_string(),
+            TokenKind::Illegal(_) => return Err(self.error("Expected an expression")),
+            TokenKind::Identifier => self.parse_identifier()?,
+            TokenKind::LeftParen => self.parse_paren_expr()?,
+            TokenKind::ArrayStart => self.parse_array_expr()?,
+            _ => return Ok(None),
+        };
+
+        self.parse_postfix(node).map(Some)
+    }
 
-        let value = self.parse_primary_expr()?;
+    /// Precedence climbing: folds binary operators binding at least as tightly as
+    /// `precedence` into `left`, recursing for the right operand of each.
+    fn parse_expr_right(&mut self, precedence: usize, mut left: Node) -> Result<Node, Error> {
+        loop {
+            let (token_prec, assoc) = match self.peek().and_then(|token| binary_operator(token.kind())) {
+                Some((token_prec, assoc)) if token_prec >= precedence => (token_prec, assoc),
+                _ => return Ok(left),
+            };
 
-        match value {
-            Some(expr) => Ok(Node::UnaryOp {
+            let op: Operator = self.eat().unwrap().value().into();
+
+            let right = match self.parse_primary_expr()? {
+                Some(right) => right,
+                None => return Err(self.error("Expected right-hand-side expression")),
+            };
+
+            // a right-associative operator lets the same operator take the right operand
+            let next_prec = match assoc {
+                Assoc::Left => token_prec + 1,
+                Assoc::Right => token_prec,
+            };
+
+            let right = self.parse_expr_right(next_prec, right)?;
+            let span = left.span.to(right.span);
+
+            left = Node::new(NodeKind::BinaryOp {
                 op,
-                rhs: Box::new(expr)
-            }),
-            None => Err(Parser::error("Expected a right-hand-side node"))
+                lhs: Box::new(left),
+                rhs: Box::new(right)
+            }, span);
         }
     }
 
-    fn parse_primary_expr(&mut self) -> Result<Option<Node<'a>>, Error> {
-        let token = self.peek();
+    fn parse_expr(&mut self) -> Result<Option<Node>, Error> {
+        match self.parse_primary_expr()? {
+            Some(left) => self.parse_expr_right(1, left).map(Some),
+            None => Ok(None),
+        }
+    }
 
-        if token.is_none() {
-            return Ok(None);
+    /// Keeps an error found while recovering, unless it repeats the location of the
+    /// previous one (a failed statement often trips over the same token twice).
+    fn record(&mut self, err: Error) {
+        if self.errors.last().is_none_or(|last| last.span() != err.span()) {
+            self.errors.push(err);
         }
+    }
+
+    /// Panic-mode recovery: skips to the end of the failed statement, which is the
+    /// next `;` outside any brackets, or the `}` closing the block when `nested`.
+    /// Bracketed groups are skipped whole, so a half-parsed `(...)` or `{...}` cannot
+    /// restart the statement early.
+    fn synchronize(&mut self, nested: bool) {
+        let mut depth = 0usize;
+
+        while let Some(token) = self.peek() {
+            match token.kind() {
+                TokenKind::Semicolon if depth == 0 => break,
+                TokenKind::BlockEnd if depth == 0 && nested => break,
+                TokenKind::LeftParen | TokenKind::ArrayStart | TokenKind::BlockStart => depth += 1,
+                TokenKind::RightParen | TokenKind::ArrayEnd | TokenKind::BlockEnd => depth = depth.saturating_sub(1),
+                _ => {},
+            }
 
-        match token.unwrap().kind() {
-            &TokenKind::Add | &TokenKind::Sub => self.parse_unary_op().map(|node| Option::from(node)),
-            &TokenKind::Number => Ok(Option::from(self.parse_number())),
-            &TokenKind::Decimal => Ok(Option::from(self.parse_decimal())),
-            &TokenKind::Identifier => self.parse_identifier().map(|node| Option::from(node)),
-            &TokenKind::LeftParen => self.parse_paren_expr().map(|node| Option::from(node)),
-            &TokenKind::ArrayStart => self.parse_array_expr().map(|node| Option::from(node)),
-            _ => Ok(None),
+            self.eat();
         }
     }
 
-    fn parse_expr_right(&mut self, precedence: usize, mut left: Node<'a>) -> Result<Node<'a>, Error> {
+    /// Parses `;`-separated statements up to the end of input, or up to the closing
+    /// `}` when `nested`. When recovering, errors are recorded and parsing resumes
+    /// after the next synchronizing token.
+    fn parse_statements(&mut self, nested: bool) -> Result<Vec<Node>, Error> {
+        let mut nodes = Vec::new();
+
         loop {
-            let mut token = self.peek();
-            let token_prec: usize = if token.is_none() {
-                0
-            } else {
-                token.unwrap().precedence()
+            match self.parse_expr() {
+                Ok(Some(expr)) => nodes.push(expr),
+                Ok(None) => {},
+                Err(err) if self.recovering => {
+                    self.record(err);
+                    self.synchronize(nested);
+                },
+                Err(err) => return Err(err),
+            }
+
+            let (semicolon, block_end) = match self.peek() {
+                Some(token) => (token.kind() == &TokenKind::Semicolon, token.kind() == &TokenKind::BlockEnd),
+                None => break,
             };
 
-            if token_prec < precedence {
-                return Ok(left);
+            if semicolon {
+                self.eat();
+            } else if (nested && block_end) || !self.recovering {
+                break;
+            } else {
+                let err = self.error(if nested { "Expected ; or BlockEnd" } else { "Expected ; or the end of input" });
+
+                self.record(err);
+
+                // a stray `}` at the top level would otherwise stop the recovery
+                if block_end {
+                    self.eat();
+                } else {
+                    self.synchronize(nested);
+                }
             }
+        }
 
-            let op: Operator = token.unwrap().value().into();
+        Ok(nodes)
+    }
 
-            // we know this token IS a binary operator
-            self.eat();
+    fn parse(&mut self) -> Result<Vec<Node>, Error> {
+        self.parse_statements(false)
+    }
 
-            let value = self.parse_primary_expr()?;
+    /// Parses the whole input, failing on any token left over.
+    fn parse_all(&mut self) -> Result<Vec<Node>, Error> {
+        let nodes = self.parse()?;
 
-            match value {
-                Some(mut right) => {
-                    token = self.peek();
+        if self.peek().is_some() {
+            return Err(self.error("Expected ; or the end of input"));
+        }
 
-                    if !token.is_none() {
-                        let next_prec = token.unwrap().precedence();
+        Ok(nodes)
+    }
+}
 
-                        if token_prec < next_prec {
-                            right = self.parse_expr_right(token_prec + 1, right)?;
-                        }
-                    }
+impl<'a> Parser<'a> {
+    pub fn new(source: &'a mut Lexer<'a>) -> Parser<'a> {
+        let eof = source.end();
 
-                    left = Node::BinaryOp {
-                        op,
-                        lhs: Box::new(left),
-                        rhs: Box::new(right)
-                    };
-                },
-                None => return Err(Parser::error("Expected right-hand-side expression"))
-            }
+        Parser {
+            source: source.peekable(),
+            last: Span::default(),
+            eof,
+            docs: Vec::new(),
+            doc: Vec::new(),
+            recovering: false,
+            errors: Vec::new(),
         }
     }
 
-    fn parse_expr(&mut self) -> Result<Option<Node<'a>>, Error> {
-        let left = self.parse_primary_expr()?;
+    pub fn run(&mut self) -> Result<Vec<Node>, Error> {
+        self.parse_all()
+    }
+
+    /// Parses the whole input, recovering from syntax errors instead of stopping at
+    /// the first. Returns the statements that parsed along with every error found,
+    /// so all of them can be fixed in one pass.
+    pub fn run_collecting(&mut self) -> (Vec<Node>, Vec<Error>) {
+        self.recovering = true;
 
-        if left.is_none() {
-            return Ok(None);
-        }
+        let nodes = match self.parse_all() {
+            Ok(nodes) => nodes,
+            Err(err) => {
+                self.record(err);
+
+                Vec::new()
+            },
+        };
 
-        self.parse_expr_right(1, left.unwrap()).map(|node| Option::from(node))
+        self.recovering = false;
+
+        (nodes, mem::take(&mut self.errors))
     }
+}
 
-    fn parse(&mut self) -> Result<Vec<Node<'a>>, Error> {
-        let mut vec = Vec::new();
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-        loop {
-            let expr = self.parse_expr()?;
+    fn parse(source: &str) -> Result<Vec<Node>, Error> {
+        let mut lexer = Lexer::new(source);
 
-            if !expr.is_none() {
-                vec.push(expr.unwrap());
-            }
+        Parser::new(&mut lexer).run()
+    }
 
-            let token = self.peek();
+    /// Names each recovered statement by what it defines or calls, alongside the
+    /// rendered errors.
+    fn collect(source: &str) -> (Vec<String>, Vec<String>) {
+        let mut lexer = Lexer::new(source);
+        let (nodes, errors) = Parser::new(&mut lexer).run_collecting();
 
-            if token.is_none() || token.unwrap().kind() != &TokenKind::Semicolon {
-                break;
-            }
+        let nodes = nodes.iter().map(|node| match &node.kind {
+            NodeKind::Assign { lhs, .. } => format!("{} =", lhs),
+            NodeKind::Function { name, body, .. } => format!("fn {} ({} statements)", name, body.0.len()),
+            NodeKind::Call { function, .. } => format!("{}()", function),
+            other => format!("{:?}", other),
+        }).collect();
 
-            self.eat();
+        (nodes, errors.iter().map(|err| err.to_string()).collect())
+    }
+
+    #[test]
+    fn run_collecting_without_errors_matches_run() {
+        let (nodes, errors) = collect("a = 1; fn f(x: Number) { x }; f(a)");
+
+        assert_eq!(nodes, ["a =", "fn f (1 statements)", "f()"]);
+        assert!(errors.is_empty());
+        assert_eq!(parse("a = 1; fn f(x: Number) { x }; f(a)").unwrap().len(), 3);
+    }
+
+    #[test]
+    fn recovery_resumes_after_each_failed_statement() {
+        let (nodes, errors) = collect("a = 1 +; b = 2; c = (3 *; d = 4");
+
+        assert_eq!(nodes, ["b =", "d ="]);
+        assert_eq!(errors, [
+            "ParserError[E0101] at 1:8: Expected right-hand-side expression, found ';'",
+            "ParserError[E0101] at 1:25: Expected right-hand-side expression, found ';'",
+        ]);
+        assert_eq!(parse("a = 1 +; b = 2").unwrap_err().to_string(), errors[0]);
+    }
+
+    #[test]
+    fn recovery_skips_bracketed_groups_whole() {
+        let (nodes, errors) = collect("fn g(x) { x + } ; h = 1");
+
+        assert_eq!(nodes, ["h ="]);
+        assert_eq!(errors, ["ParserError[E0101] at 1:7: Expected Colon, found ')'"]);
+
+        let (nodes, errors) = collect("x = [1, (2 }]; y = 3");
+
+        assert_eq!(nodes, ["y ="]);
+        assert_eq!(errors, ["ParserError[E0101] at 1:12: Expected RightParen, found '}'"]);
+    }
+
+    #[test]
+    fn recovery_inside_a_block_keeps_the_block() {
+        let (nodes, errors) = collect("fn f(x: Number) { y = x +; x }; z = 1");
+
+        assert_eq!(nodes, ["fn f (1 statements)", "z ="]);
+        assert_eq!(errors, ["ParserError[E0101] at 1:26: Expected right-hand-side expression, found ';'"]);
+    }
+
+    #[test]
+    fn stray_block_ends_are_reported_once_each() {
+        let (nodes, errors) = collect("} ; a = 1 }");
+
+        assert_eq!(nodes, ["a ="]);
+        assert_eq!(errors, [
+            "ParserError[E0101] at 1:1: Expected ; or the end of input, found '}'",
+            "ParserError[E0101] at 1:11: Expected ; or the end of input, found '}'",
+        ]);
+    }
+
+    #[test]
+    fn string_escapes_are_decoded() {
+        let nodes = parse(r#""a\tb\n\"q\" \\ \0""#).unwrap();
+
+        match &nodes[0].kind {
+            NodeKind::String(value) => assert_eq!(value, "a\tb\n\"q\" \\ \0"),
+            other => panic!("expected a string, got {:?}", other),
         }
+    }
 
-        Ok(vec)
+    #[test]
+    fn malformed_strings_are_errors() {
+        assert!(parse(r#""abc"#).is_err());
+        assert!(parse(r#""a\qb""#).is_err());
     }
-}
 
-impl<'a> Parser<'a> {
-    pub fn new(source: &'a mut Lexer<'a>) -> Parser {
-        Parser {
-            source: source.peekable(),
+    /// The arguments of a single call, or the error parsing it.
+    fn arguments(source: &str) -> Result<Vec<String>, String> {
+        let nodes = parse(source).map_err(|err| err.to_string())?;
+
+        match &nodes[0].kind {
+            NodeKind::Call { arguments, .. } => Ok(arguments.iter().map(|arg| arg.to_string()).collect()),
+            other => panic!("expected a call, got {:?}", other),
         }
     }
 
-    pub fn run(&mut self) -> Result<Vec<Node<'a>>, Error> {
-        self.parse()
+    #[test]
+    fn call_arguments_are_separated_by_commas() {
+        assert_eq!(arguments("f()").unwrap(), Vec::<String>::new());
+        assert_eq!(arguments("f(a, 1)").unwrap(), ["VariableNode(a)", "NumberNode(1)"]);
+        assert_eq!(arguments("f(a, b,)").unwrap(), ["VariableNode(a)", "VariableNode(b)"]);
+        assert_eq!(arguments("f(\n  a,\n  b\n)").unwrap().len(), 2);
+    }
+
+    #[test]
+    fn malformed_argument_lists_are_errors() {
+        assert_eq!(arguments("f(a,,b)").unwrap_err(), "ParserError[E0101] at 1:5: Expected argument, found ','");
+        assert_eq!(arguments("f(,)").unwrap_err(), "ParserError[E0101] at 1:3: Expected argument, found ','");
+        assert_eq!(arguments("f(a b)").unwrap_err(), "ParserError[E0101] at 1:5: Expected Separator or RightParen, found 'b'");
+        assert_eq!(arguments("f(a").unwrap_err(), "ParserError[E0102] at 1:4: Expected RightParen");
+    }
+
+    fn tree(source: &str) -> String {
+        parse(source).unwrap()[0].to_string()
+    }
+
+    #[test]
+    fn exponents_are_right_associative_and_bind_tighter_than_negation() {
+        assert_eq!(tree("2^3^2"), "BinaryOpNode(NumberNode(2), ^, BinaryOpNode(NumberNode(3), ^, NumberNode(2)))");
+        assert_eq!(tree("-2^2"), "UnaryOpNode(-, BinaryOpNode(NumberNode(2), ^, NumberNode(2)))");
+        assert_eq!(tree("-a .^ 2"), "UnaryOpNode(-, BinaryOpNode(VariableNode(a), .^, NumberNode(2)))");
+        assert_eq!(tree("2^-1"), "BinaryOpNode(NumberNode(2), ^, UnaryOpNode(-, NumberNode(1)))");
+    }
+
+    #[test]
+    fn other_operators_are_left_associative_by_precedence() {
+        assert_eq!(tree("1 - 2 - 3"), "BinaryOpNode(BinaryOpNode(NumberNode(1), -, NumberNode(2)), -, NumberNode(3))");
+        assert_eq!(tree("8 / 4 / 2"), "BinaryOpNode(BinaryOpNode(NumberNode(8), /, NumberNode(4)), /, NumberNode(2))");
+        assert_eq!(tree("1 + 2 * 3"), "BinaryOpNode(NumberNode(1), +, BinaryOpNode(NumberNode(2), *, NumberNode(3)))");
+        assert_eq!(tree("a .* b ^ 2"), "BinaryOpNode(VariableNode(a), .*, BinaryOpNode(VariableNode(b), ^, NumberNode(2)))");
+        assert_eq!(tree("2 * -3"), "BinaryOpNode(NumberNode(2), *, UnaryOpNode(-, NumberNode(3)))");
+        assert_eq!(tree("1 < 2 + 3"), "BinaryOpNode(NumberNode(1), <, BinaryOpNode(NumberNode(2), +, NumberNode(3)))");
+        assert_eq!(tree("0..n + 1"), "BinaryOpNode(NumberNode(0), .., BinaryOpNode(VariableNode(n), +, NumberNode(1)))");
+    }
+
+    /// The doc comment of each function in the source.
+    fn docs(source: &str) -> Vec<Option<String>> {
+        parse(source).unwrap().into_iter().filter_map(|node| match node.kind {
+            NodeKind::Function { doc, .. } => Some(doc),
+            _ => None,
+        }).collect()
+    }
+
+    #[test]
+    fn doc_comments_attach_to_the_following_function() {
+        assert_eq!(docs("/// Squares x.\n/// Twice.\nfn f(x: Number) { x^2 }"), [Some(String::from("Squares x.\nTwice."))]);
+        assert_eq!(docs("fn f(x: Number) { x }"), [None]);
+        assert_eq!(docs("/// About a.\na = 1; fn f(x: Number) { x }"), [None]);
+        assert_eq!(docs("// plain\nfn f(x: Number) { /// inner\n x }"), [None]);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn trailing_input_is_rejected() {
+        let error = |source| parse(source).unwrap_err().to_string();
+
+        assert_eq!(error("a = 1 ) b"), "ParserError[E0101] at 1:7: Expected ; or the end of input, found ')'");
+        assert_eq!(error("f(1))"), "ParserError[E0101] at 1:5: Expected ; or the end of input, found ')'");
+        assert_eq!(error("1 2"), "ParserError[E0101] at 1:3: Expected ; or the end of input, found '2'");
+        assert_eq!(parse("a = 1;").unwrap().len(), 1);
+        assert_eq!(parse(";; a = 1").unwrap().len(), 1);
+    }
+
+    #[test]
+    fn unknown_and_illegal_tokens_are_reported() {
+        let error = |source| parse(source).unwrap_err().to_string();
+
+        assert_eq!(error("2 $ 3"), "ParserError[E0101] at 1:3: Expected ; or the end of input, found unknown token '$'");
+        assert_eq!(error("x = 1.2.3"), "ParserError[E0100] at 1:5: Invalid token '1.2.3': more than one decimal point");
+    }
+}
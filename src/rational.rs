@@ -0,0 +1,194 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::ops;
+use crate::bigint::BigInt;
+use crate::error::Error;
+use crate::memory::division_by_zero;
+
+/// An exact fraction, kept in lowest terms with a positive denominator.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rational {
+    numer: BigInt,
+    denom: BigInt,
+}
+
+impl Rational {
+    /// Builds `numer / denom` in lowest terms, or `None` if `denom` is zero.
+    pub fn new(numer: BigInt, denom: BigInt) -> Option<Rational> {
+        if denom.is_zero() {
+            return None;
+        }
+
+        let gcd = numer.gcd(&denom);
+        let (mut numer, _) = numer.div_rem(&gcd)?;
+        let (mut denom, _) = denom.div_rem(&gcd)?;
+
+        if denom.is_negative() {
+            numer = -numer;
+            denom = -denom;
+        }
+
+        Some(Rational {
+            numer,
+            denom,
+        })
+    }
+
+    pub fn from_integer(value: BigInt) -> Rational {
+        Rational {
+            numer: value,
+            denom: BigInt::one(),
+        }
+    }
+
+    pub fn numer(&self) -> &BigInt {
+        &self.numer
+    }
+
+    pub fn denom(&self) -> &BigInt {
+        &self.denom
+    }
+
+    pub fn is_integer(&self) -> bool {
+        self.denom == BigInt::one()
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.numer.is_zero()
+    }
+
+    pub fn to_f64(&self) -> f64 {
+        self.numer.to_f64() / self.denom.to_f64()
+    }
+
+    /// The largest integer not greater than the fraction.
+    pub fn floor(&self) -> BigInt {
+        let (q, r) = self.numer.div_rem(&self.denom).unwrap();
+
+        if r.is_negative() { &q - &BigInt::one() } else { q }
+    }
+
+    /// The smallest integer not less than the fraction.
+    pub fn ceil(&self) -> BigInt {
+        -(-self.clone()).floor()
+    }
+
+    /// The nearest integer, rounding halves away from zero.
+    pub fn round(&self) -> BigInt {
+        let half = Rational::new(BigInt::one(), BigInt::from(2)).unwrap();
+
+        if self.numer.is_negative() {
+            -(&-self.clone() + &half).floor()
+        } else {
+            (self + &half).floor()
+        }
+    }
+
+    /// `self / other`, or `None` when `other` is zero.
+    pub fn checked_div(&self, other: &Rational) -> Option<Rational> {
+        Rational::new(&self.numer * &other.denom, &self.denom * &other.numer)
+    }
+
+    /// Raises to an integer power. Fails for a negative power of zero, or when the
+    /// result is too large (see `BigInt::pow`).
+    pub fn pow(&self, exp: i64) -> Result<Rational, Error> {
+        let magnitude = exp.unsigned_abs();
+        let (numer, denom) = (self.numer.pow(magnitude)?, self.denom.pow(magnitude)?);
+
+        if exp < 0 {
+            Rational::new(denom, numer).ok_or_else(division_by_zero)
+        } else {
+            Ok(Rational::new(numer, denom).expect("the denominator is positive"))
+        }
+    }
+}
+
+impl Ord for Rational {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (&self.numer * &other.denom).cmp(&(&other.numer * &self.denom))
+    }
+}
+
+impl PartialOrd for Rational {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl ops::Neg for Rational {
+    type Output = Rational;
+
+    fn neg(self) -> Self::Output {
+        Rational {
+            numer: -self.numer,
+            denom: self.denom,
+        }
+    }
+}
+
+impl ops::Add<&Rational> for &Rational {
+    type Output = Rational;
+
+    fn add(self, other: &Rational) -> Self::Output {
+        let numer = &(&self.numer * &other.denom) + &(&other.numer * &self.denom);
+
+        Rational::new(numer, &self.denom * &other.denom).unwrap()
+    }
+}
+
+impl ops::Sub<&Rational> for &Rational {
+    type Output = Rational;
+
+    fn sub(self, other: &Rational) -> Self::Output {
+        self + &-other.clone()
+    }
+}
+
+impl ops::Mul<&Rational> for &Rational {
+    type Output = Rational;
+
+    fn mul(self, other: &Rational) -> Self::Output {
+        Rational::new(&self.numer * &other.numer, &self.denom * &other.denom).unwrap()
+    }
+}
+
+impl fmt::Display for Rational {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_integer() {
+            write!(f, "{}", self.numer)
+        } else {
+            write!(f, "{}/{}", self.numer, self.denom)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ErrorCode;
+
+    fn ratio(numer: i64, denom: i64) -> Rational {
+        Rational::new(BigInt::from(numer), BigInt::from(denom)).unwrap()
+    }
+
+    #[test]
+    fn fractions_are_normalised() {
+        assert_eq!(ratio(4, -6), ratio(-2, 3));
+        assert_eq!(ratio(-2, 3).to_string(), "-2/3");
+        assert_eq!(Rational::new(BigInt::one(), BigInt::zero()), None);
+    }
+
+    #[test]
+    fn arithmetic_stays_exact() {
+        assert_eq!(&ratio(1, 3) + &ratio(1, 6), ratio(1, 2));
+        assert_eq!(ratio(1, 3).checked_div(&ratio(-2, 9)), Some(ratio(-3, 2)));
+        assert_eq!(ratio(-7, 2).floor(), BigInt::from(-4));
+        assert_eq!(ratio(2, 3).pow(-3).unwrap(), ratio(27, 8));
+    }
+
+    #[test]
+    fn pow_rejects_zero_and_huge_results() {
+        assert_eq!(ratio(0, 1).pow(-1).unwrap_err().code(), ErrorCode::InvalidOperation);
+        assert_eq!(ratio(1, 2).pow(100_000_000).unwrap_err().code(), ErrorCode::Overflow);
+    }
+}
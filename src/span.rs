@@ -0,0 +1,60 @@
+use std::fmt;
+
+/// A range of source text, as byte offsets plus the line and column of its start.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize, line: usize, column: usize) -> Span {
+        Span {
+            start,
+            end,
+            line,
+            column,
+        }
+    }
+
+    /// Returns a span covering everything from the start of `self` to the end of `other`.
+    pub fn to(self, other: Span) -> Span {
+        Span {
+            end: other.end.max(self.end),
+            ..self
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.end - self.start
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+}
+
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn to_keeps_the_start_and_extends_the_end() {
+        let a = Span::new(4, 6, 2, 1);
+        let b = Span::new(10, 12, 3, 3);
+
+        assert_eq!(a.to(b), Span::new(4, 12, 2, 1));
+        assert_eq!(a.to(Span::new(5, 5, 2, 2)), a);
+        assert_eq!(a.to(b).len(), 8);
+        assert!(Span::new(3, 3, 1, 4).is_empty());
+        assert_eq!(b.to_string(), "3:3");
+    }
+}
@@ -0,0 +1,318 @@
+use std::cmp::Ordering;
+use crate::ast::Operator;
+use crate::error::{Error, ErrorCode};
+use crate::matrix::Matrix;
+use crate::memory::Value;
+
+/// A reduction input: either a flat list of numbers or a table of equal-length rows,
+/// taken from a matrix or an array of arrays.
+struct Table {
+    rows: Vec<Vec<Value>>,
+    flat: bool,
+}
+
+fn not_numeric(name: &str, value: &Value) -> Error {
+    Error::runtime(format!("{} expects numbers, got {}", name, value.kind()))
+        .with_code(ErrorCode::TypeMismatch)
+}
+
+fn scalars(name: &str, items: Vec<Value>) -> Result<Vec<Value>, Error> {
+    match items.iter().find(|item| !item.is_scalar()) {
+        Some(item) => Err(not_numeric(name, item)),
+        None => Ok(items),
+    }
+}
+
+impl Table {
+    fn new(name: &str, value: Value) -> Result<Table, Error> {
+        let (rows, flat) = match value {
+            Value::Matrix(mat) => (mat.to_rows(), false),
+            Value::Array(items) if !items.is_empty() && items.iter().all(|item| matches!(item, Value::Array(_))) => (items, false),
+            Value::Array(items) => (vec![Value::Array(items)], true),
+            scalar if scalar.is_scalar() => (vec![Value::Array(vec![scalar])], true),
+            other => return Err(not_numeric(name, &other)),
+        };
+
+        let rows = rows.into_iter()
+            .map(|row| match row {
+                Value::Array(items) => scalars(name, items),
+                other => Err(not_numeric(name, &other)),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        if let Some(row) = rows.iter().find(|row| row.len() != rows[0].len()) {
+            return Err(Error::runtime(format!(
+                "{} expects rows of the same length, expected {} got {}", name, rows[0].len(), row.len()
+            )).with_code(ErrorCode::ShapeMismatch));
+        }
+
+        Ok(Table { rows, flat })
+    }
+
+    fn columns(&self) -> Vec<Vec<Value>> {
+        let cols = self.rows.first().map_or(0, Vec::len);
+
+        (0..cols).map(|j| self.rows.iter().map(|row| row[j].clone()).collect()).collect()
+    }
+
+    /// Splits the table into the lists that each output value is computed from.
+    /// `None` reduces everything, axis 0 runs down the columns and axis 1 along the rows.
+    fn lanes(self, name: &str, axis: Option<i64>) -> Result<Vec<Vec<Value>>, Error> {
+        match axis {
+            None => Ok(vec![self.rows.into_iter().flatten().collect()]),
+            Some(0) if self.flat => Ok(self.rows),
+            Some(0) => Ok(self.columns()),
+            Some(1) if !self.flat => Ok(self.rows),
+            Some(axis) => Err(Error::runtime(format!(
+                "{}: axis {} is out of range for a {}-dimensional value", name, axis, if self.flat { 1 } else { 2 }
+            )).with_code(ErrorCode::IndexOutOfBounds)),
+        }
+    }
+}
+
+/// Arguments after the data: an optional `Number` axis and, for `var` and `std`, an
+/// optional `Bool` that selects the sample (`true`) rather than population variance.
+struct Options {
+    axis: Option<i64>,
+    sample: bool,
+}
+
+fn options(name: &str, args: &[Value], allow_sample: bool) -> Result<Options, Error> {
+    let mut opts = Options { axis: None, sample: false };
+    let usage = if allow_sample { "(x[, axis][, sample])" } else { "(x[, axis])" };
+
+    match args {
+        [] => {},
+        [Value::Number(axis)] => opts.axis = Some(*axis),
+        [Value::Bool(sample)] if allow_sample => opts.sample = *sample,
+        [Value::Number(axis), Value::Bool(sample)] if allow_sample => {
+            opts.axis = Some(*axis);
+            opts.sample = *sample;
+        },
+        _ => return Err(Error::runtime(format!("{} expects {}", name, usage))
+            .with_code(ErrorCode::ArityMismatch)),
+    }
+
+    Ok(opts)
+}
+
+fn split(name: &str, args: Vec<Value>) -> Result<(Value, Vec<Value>), Error> {
+    let mut args = args.into_iter();
+
+    match args.next() {
+        Some(value) => Ok((value, args.collect())),
+        None => Err(Error::runtime(format!("{} expects at least 1 argument(s), got 0", name))
+            .with_code(ErrorCode::ArityMismatch)),
+    }
+}
+
+/// Reduces each lane to a single value; the result is a scalar unless an axis of a
+/// two-dimensional value was given.
+fn reduce_with(
+    name: &str,
+    value: Value,
+    opts: &Options,
+    f: impl Fn(Vec<Value>) -> Result<Value, Error>,
+) -> Result<Value, Error> {
+    let table = Table::new(name, value)?;
+    let keep = opts.axis.is_some() && !table.flat;
+    let mut res = table.lanes(name, opts.axis)?
+        .into_iter()
+        .map(f)
+        .collect::<Result<Vec<Value>, Error>>()?;
+
+    if keep {
+        Ok(Value::Array(res))
+    } else {
+        Ok(res.pop().unwrap_or(Value::Number(0)))
+    }
+}
+
+fn reduce(name: &str, args: Vec<Value>, f: impl Fn(Vec<Value>) -> Result<Value, Error>) -> Result<Value, Error> {
+    let (value, rest) = split(name, args)?;
+
+    reduce_with(name, value, &options(name, &rest, false)?, f)
+}
+
+fn empty(name: &str) -> Error {
+    Error::runtime(format!("{} of an empty array", name)).with_code(ErrorCode::ShapeMismatch)
+}
+
+fn fold(lane: Vec<Value>, init: Value, op: &Operator) -> Result<Value, Error> {
+    lane.into_iter().try_fold(init, |acc, x| acc.apply(op, x))
+}
+
+fn mean_of(name: &str, lane: Vec<Value>) -> Result<Value, Error> {
+    if lane.is_empty() {
+        return Err(empty(name));
+    }
+
+    let n = lane.len() as i64;
+
+    fold(lane, Value::Number(0), &Operator::Add)? / Value::Number(n)
+}
+
+fn real(name: &str, lane: Vec<Value>) -> Result<Vec<f64>, Error> {
+    lane.iter()
+        .map(|x| match x {
+            Value::Complex(_) => Err(Error::runtime(format!("{} expects real numbers, got Complex", name))
+                .with_code(ErrorCode::TypeMismatch)),
+            x => Ok(x.as_f64().unwrap_or(f64::NAN)),
+        })
+        .collect()
+}
+
+/// Sorts real numbers, keeping exact values exact.
+fn sorted(name: &str, lane: Vec<Value>) -> Result<Vec<Value>, Error> {
+    real(name, lane.clone())?;
+
+    let mut lane = lane;
+
+    lane.sort_by(|a, b| a.partial_cmp_scalar(b).unwrap_or(Ordering::Equal));
+
+    Ok(lane)
+}
+
+fn variance(name: &str, lane: Vec<Value>, sample: bool) -> Result<f64, Error> {
+    let xs = real(name, lane)?;
+    let n = xs.len();
+    let dof = if sample { n.saturating_sub(1) } else { n };
+
+    if dof == 0 {
+        return Err(Error::runtime(format!(
+            "{} needs at least {} value(s)", name, if sample { 2 } else { 1 }
+        )).with_code(ErrorCode::ShapeMismatch));
+    }
+
+    let mean = xs.iter().sum::<f64>() / n as f64;
+
+    Ok(xs.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / dof as f64)
+}
+
+/// Index of the lane entry that compares as `wanted` against all others; the first wins ties.
+fn best_index(name: &str, lane: &[Value], wanted: Ordering) -> Result<usize, Error> {
+    if lane.is_empty() {
+        return Err(empty(name));
+    }
+
+    let mut best = 0;
+
+    for (i, x) in lane.iter().enumerate().skip(1) {
+        match x.partial_cmp_scalar(&lane[best]) {
+            Some(ordering) if ordering == wanted => best = i,
+            Some(_) => {},
+            None => return Err(Error::runtime(format!(
+                "{} cannot order {} and {}", name, x.kind(), lane[best].kind()
+            )).with_code(ErrorCode::InvalidOperation)),
+        }
+    }
+
+    Ok(best)
+}
+
+pub fn sum(args: Vec<Value>) -> Result<Value, Error> {
+    reduce("sum", args, |lane| fold(lane, Value::Number(0), &Operator::Add))
+}
+
+pub fn prod(args: Vec<Value>) -> Result<Value, Error> {
+    reduce("prod", args, |lane| fold(lane, Value::Number(1), &Operator::Mul))
+}
+
+pub fn mean(args: Vec<Value>) -> Result<Value, Error> {
+    reduce("mean", args, |lane| mean_of("mean", lane))
+}
+
+pub fn median(args: Vec<Value>) -> Result<Value, Error> {
+    reduce("median", args, |lane| {
+        let lane = sorted("median", lane)?;
+        let mid = lane.len() / 2;
+
+        match lane.len() {
+            0 => Err(empty("median")),
+            n if n % 2 == 1 => Ok(lane[mid].clone()),
+            _ => mean_of("median", lane[mid - 1..=mid].to_vec()),
+        }
+    })
+}
+
+/// `var(x[, axis][, sample])` is the population variance, or the sample variance
+/// (dividing by `n - 1`) when `sample` is `true`.
+pub fn var(args: Vec<Value>) -> Result<Value, Error> {
+    let (value, rest) = split("var", args)?;
+    let opts = options("var", &rest, true)?;
+
+    reduce_with("var", value, &opts, |lane| variance("var", lane, opts.sample).map(Value::Decimal))
+}
+
+/// `std(x[, axis][, sample])` is the square root of `var` with the same arguments.
+pub fn std(args: Vec<Value>) -> Result<Value, Error> {
+    let (value, rest) = split("std", args)?;
+    let opts = options("std", &rest, true)?;
+
+    reduce_with("std", value, &opts, |lane| variance("std", lane, opts.sample).map(|v| Value::Decimal(v.sqrt())))
+}
+
+/// The smallest (`Ordering::Less`) or largest entry, over everything or along an axis.
+pub(crate) fn extreme(name: &str, args: Vec<Value>, wanted: Ordering) -> Result<Value, Error> {
+    reduce(name, args, |lane| best_index(name, &lane, wanted).map(|i| lane[i].clone()))
+}
+
+pub fn argmin(args: Vec<Value>) -> Result<Value, Error> {
+    reduce("argmin", args, |lane| best_index("argmin", &lane, Ordering::Less).map(|i| Value::Number(i as i64)))
+}
+
+pub fn argmax(args: Vec<Value>) -> Result<Value, Error> {
+    reduce("argmax", args, |lane| best_index("argmax", &lane, Ordering::Greater).map(|i| Value::Number(i as i64)))
+}
+
+/// Running totals, flattened without an axis and keeping the input's shape with one.
+fn accumulate(name: &str, args: Vec<Value>, op: &Operator) -> Result<Value, Error> {
+    let (value, rest) = split(name, args)?;
+    let opts = options(name, &rest, false)?;
+    let matrix = matches!(value, Value::Matrix(_));
+    let table = Table::new(name, value)?;
+    let flat = table.flat;
+
+    let lanes = table.lanes(name, opts.axis)?
+        .into_iter()
+        .map(|lane| {
+            let mut acc: Option<Value> = None;
+
+            lane.into_iter()
+                .map(|x| {
+                    let next = match acc.take() {
+                        Some(acc) => acc.apply(op, x)?,
+                        None => x,
+                    };
+
+                    acc = Some(next.clone());
+
+                    Ok(next)
+                })
+                .collect::<Result<Vec<Value>, Error>>()
+        })
+        .collect::<Result<Vec<_>, Error>>()?;
+
+    let rows: Vec<Vec<Value>> = match opts.axis {
+        None => return Ok(Value::Array(lanes.into_iter().flatten().collect())),
+        Some(_) if flat => return Ok(Value::Array(lanes.into_iter().flatten().collect())),
+        // columns were accumulated, so turn them back into rows
+        Some(0) => Table { rows: lanes, flat: false }.columns(),
+        Some(_) => lanes,
+    };
+
+    let rows: Vec<Value> = rows.into_iter().map(Value::Array).collect();
+
+    match Matrix::from_values(&rows) {
+        Some(mat) if matrix => mat.map(Value::Matrix),
+        _ => Ok(Value::Array(rows)),
+    }
+}
+
+pub fn cumsum(args: Vec<Value>) -> Result<Value, Error> {
+    accumulate("cumsum", args, &Operator::Add)
+}
+
+pub fn cumprod(args: Vec<Value>) -> Result<Value, Error> {
+    accumulate("cumprod", args, &Operator::Mul)
+}
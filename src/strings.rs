@@ -0,0 +1,176 @@
+use std::iter::Peekable;
+use std::str::Chars;
+use crate::error::{Error, ErrorCode};
+use crate::linalg::expect_arity;
+use crate::memory::Value;
+
+/// The most decimal places a `{:.N}` placeholder may ask for; an `f64` has no more
+/// than 17 significant digits anyway.
+const MAX_PRECISION: usize = 100;
+
+fn format_error(message: &str) -> Error {
+    Error::runtime(format!("format: {}", message)).with_code(ErrorCode::InvalidOperation)
+}
+
+/// The number of characters in a string, items in an array or rows in a matrix.
+pub fn len(args: Vec<Value>) -> Result<Value, Error> {
+    expect_arity("len", &args, 1)?;
+
+    match &args[0] {
+        Value::String(value) => Ok(Value::Number(value.chars().count() as i64)),
+        Value::Array(items) => Ok(Value::Number(items.len() as i64)),
+        Value::Matrix(mat) => Ok(Value::Number(mat.rows() as i64)),
+        other => Err(Error::runtime(format!("len expects a String, Array or Matrix, got {}", other.kind()))
+            .with_code(ErrorCode::TypeMismatch)),
+    }
+}
+
+/// Converts a value to the string it displays as.
+pub fn str(args: Vec<Value>) -> Result<Value, Error> {
+    expect_arity("str", &args, 1)?;
+
+    Ok(Value::String(format!("{:#}", args[0])))
+}
+
+/// Writes a value for a `format` placeholder, rounding numbers to `precision`
+/// decimal places when one is given.
+fn text(value: &Value, precision: Option<usize>) -> String {
+    let precision = match precision {
+        Some(precision) => precision,
+        None => return format!("{:#}", value),
+    };
+
+    match value {
+        Value::Complex(z) => format!("{:.*}{:+.*}i", precision, z.re, precision, z.im),
+        Value::Array(items) => {
+            let items: Vec<String> = items.iter().map(|item| text(item, Some(precision))).collect();
+
+            format!("[{}]", items.join(", "))
+        },
+        Value::Matrix(mat) => text(&Value::Array(mat.to_rows()), Some(precision)),
+        value => match value.as_f64() {
+            Some(x) => format!("{:.*}", precision, x),
+            None => format!("{:#}", value),
+        },
+    }
+}
+
+/// Reads a placeholder after its `{`: `}` or `:.N}`, giving the precision if any.
+fn placeholder(chars: &mut Peekable<Chars>) -> Result<Option<usize>, Error> {
+    let mut spec = String::new();
+
+    loop {
+        match chars.next() {
+            Some('}') => break,
+            Some(c) => spec.push(c),
+            None => return Err(format_error("unclosed '{' in template")),
+        }
+    }
+
+    match spec.strip_prefix(":.") {
+        _ if spec.is_empty() => Ok(None),
+        Some(digits) => match digits.parse::<usize>() {
+            Ok(precision) if precision > MAX_PRECISION => Err(format_error(&format!(
+                "precision too large in '{{{}}}'; the most is {}", spec, MAX_PRECISION
+            ))),
+            Ok(precision) => Ok(Some(precision)),
+            Err(_) => Err(format_error(&format!("invalid precision in '{{{}}}'", spec))),
+        },
+        None => Err(format_error(&format!("unsupported placeholder '{{{}}}'; use {{}} or {{:.N}}", spec))),
+    }
+}
+
+/// `format(template, args...)` replaces each `{}` in the template with the next
+/// argument, or each `{:.N}` with it rounded to N decimal places. `{{` and `}}`
+/// are literal braces.
+pub fn format(args: Vec<Value>) -> Result<Value, Error> {
+    let mut args = args.into_iter();
+
+    let template = match args.next() {
+        Some(Value::String(template)) => template,
+        Some(other) => return Err(Error::runtime(format!("format expects a String template, got {}", other.kind()))
+            .with_code(ErrorCode::TypeMismatch)),
+        None => return Err(Error::runtime("format expects at least 1 argument(s), got 0")
+            .with_code(ErrorCode::ArityMismatch)),
+    };
+
+    let mut out = String::with_capacity(template.len());
+    let mut chars = template.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '{' | '}' if chars.peek() == Some(&c) => {
+                chars.next();
+                out.push(c);
+            },
+            '{' => {
+                let precision = placeholder(&mut chars)?;
+
+                match args.next() {
+                    Some(value) => out.push_str(&text(&value, precision)),
+                    None => return Err(Error::runtime("format: the template has more placeholders than arguments")
+                        .with_code(ErrorCode::ArityMismatch)),
+                }
+            },
+            '}' => return Err(format_error("unmatched '}' in template")),
+            c => out.push(c),
+        }
+    }
+
+    if args.len() > 0 {
+        return Err(Error::runtime(format!("format: {} argument(s) left over after filling the template", args.len()))
+            .with_code(ErrorCode::ArityMismatch));
+    }
+
+    Ok(Value::String(out))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn string(text: &str) -> Value {
+        Value::String(String::from(text))
+    }
+
+    fn format_with(args: Vec<Value>) -> Result<String, ErrorCode> {
+        match format(args) {
+            Ok(Value::String(text)) => Ok(text),
+            Ok(other) => panic!("format returned {:?}", other),
+            Err(err) => Err(err.code()),
+        }
+    }
+
+    #[test]
+    fn format_fills_placeholders() {
+        assert_eq!(format_with(vec![string("{} + {} = {}"), Value::Number(1), Value::Number(2), Value::Number(3)]), Ok(String::from("1 + 2 = 3")));
+        assert_eq!(format_with(vec![string("{:.2}"), Value::Decimal(1.23456)]), Ok(String::from("1.23")));
+        assert_eq!(format_with(vec![string("{:.1}"), Value::Array(vec![Value::Number(1), Value::Decimal(0.25)])]), Ok(String::from("[1.0, 0.2]")));
+        assert_eq!(format_with(vec![string("{{{}}}"), string("x")]), Ok(String::from("{x}")));
+    }
+
+    #[test]
+    fn format_rejects_bad_templates() {
+        assert_eq!(format_with(vec![string("{:.999999999999}"), Value::Number(1)]), Err(ErrorCode::InvalidOperation));
+        assert_eq!(format_with(vec![string("{:x}"), Value::Number(1)]), Err(ErrorCode::InvalidOperation));
+        assert_eq!(format_with(vec![string("{")]), Err(ErrorCode::InvalidOperation));
+        assert_eq!(format_with(vec![string("}")]), Err(ErrorCode::InvalidOperation));
+        assert_eq!(format_with(vec![string("{} {}"), Value::Number(1)]), Err(ErrorCode::ArityMismatch));
+        assert_eq!(format_with(vec![string("{}"), Value::Number(1), Value::Number(2)]), Err(ErrorCode::ArityMismatch));
+        assert_eq!(format_with(vec![Value::Number(1)]), Err(ErrorCode::TypeMismatch));
+    }
+
+    #[test]
+    fn len_counts_characters_items_and_rows() {
+        assert_eq!(len(vec![string("héllo")]).unwrap(), Value::Number(5));
+        assert_eq!(len(vec![Value::Array(vec![Value::Number(1), Value::Number(2)])]).unwrap(), Value::Number(2));
+        assert_eq!(len(vec![Value::Number(1)]).unwrap_err().code(), ErrorCode::TypeMismatch);
+    }
+
+    #[test]
+    fn str_matches_plain_display() {
+        assert_eq!(str(vec![Value::Decimal(0.5)]).unwrap(), string("0.5"));
+        assert_eq!(str(vec![Value::Array(vec![Value::Number(1), Value::Bool(true)])]).unwrap(), string("[1, true]"));
+        assert_eq!(str(vec![string("a")]).unwrap(), string("a"));
+    }
+}
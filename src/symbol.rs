@@ -6,36 +6,54 @@ pub enum Symbol<'a> {
     }
 }
 
-pub const Number: Symbol = Symbol::Native { name: "Number" };
-pub const Decimal: Symbol = Symbol::Native { name: "Decimal" };
-pub const Array: Symbol = Symbol::Native { name: "Array" };
+pub const NUMBER: Symbol = Symbol::Native { name: "Number" };
+pub const DECIMAL: Symbol = Symbol::Native { name: "Decimal" };
+pub const BIGINT: Symbol = Symbol::Native { name: "BigInt" };
+pub const RATIONAL: Symbol = Symbol::Native { name: "Rational" };
+pub const COMPLEX: Symbol = Symbol::Native { name: "Complex" };
+pub const BOOL: Symbol = Symbol::Native { name: "Bool" };
+pub const STRING: Symbol = Symbol::Native { name: "String" };
+pub const ARRAY: Symbol = Symbol::Native { name: "Array" };
+pub const MATRIX: Symbol = Symbol::Native { name: "Matrix" };
+pub const FUNCTION: Symbol = Symbol::Native { name: "Function" };
 
 pub struct SymbolTable<'a> {
     members: HashMap<&'a str, Symbol<'a>>
 }
 
+impl<'a> Default for SymbolTable<'a> {
+    fn default() -> Self {
+        SymbolTable::new()
+    }
+}
+
 impl<'a> SymbolTable<'a> {
     pub fn new() -> SymbolTable<'a> {
         let mut table = SymbolTable { members: HashMap::new() };
 
-        table.define(Number);
-        table.define(Decimal);
-        table.define(Array);
+        table.define(NUMBER);
+        table.define(DECIMAL);
+        table.define(BIGINT);
+        table.define(RATIONAL);
+        table.define(COMPLEX);
+        table.define(BOOL);
+        table.define(STRING);
+        table.define(ARRAY);
+        table.define(MATRIX);
+        table.define(FUNCTION);
 
         table
     }
 
     pub fn define(&mut self, symbol: Symbol<'a>) {
-        let symName: &'a str;
-
-        match symbol {
-            Symbol::Native { name } => symName = name,
-        }
+        let sym_name = match symbol {
+            Symbol::Native { name } => name,
+        };
 
-        self.members.insert(symName, symbol);
+        self.members.insert(sym_name, symbol);
     }
 
-    pub fn lookup(&self, name: &'a str) -> Option<&Symbol<'a>> {
+    pub fn lookup(&self, name: &str) -> Option<&Symbol<'a>> {
         self.members.get(name)
     }
 }
\ No newline at end of file